@@ -0,0 +1,280 @@
+use crate::constants::*;
+use crate::SerialNumber;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BoardKind {
+    Jellybean,
+    Jawbreaker,
+    HackRFOne,
+    HackRFOneR9,
+    Rad1o,
+    Praline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    // what the board is specified for; advisory, tuning outside it works
+    // with degraded performance
+    pub freq_range: RangeInclusive<u64>,
+    // what the firmware will tune to, enforced by `set_freq`
+    pub tuning_range: RangeInclusive<u64>,
+    pub lna_gain_max: u16,
+    pub vga_gain_max: u16,
+    pub txvga_gain_max: u16,
+    pub amp: bool,
+    pub bias_tee: bool,
+    pub clkin: bool,
+    pub clkout: bool,
+}
+
+impl BoardKind {
+    // HackRF One (both the original and r9) and Praline share a product id,
+    // only the board id read from firmware tells them apart. A board in DFU
+    // mode has no firmware to drive and maps to no kind.
+    pub fn from_usb_pid(pid: u16) -> Option<BoardKind> {
+        match pid {
+            HACKRF_JAWBREAKER_USB_PID => Some(BoardKind::Jawbreaker),
+            HACKRF_ONE_USB_PID => Some(BoardKind::HackRFOne),
+            RAD1O_USB_PID => Some(BoardKind::Rad1o),
+            _ => None,
+        }
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        let hackrf_one = Capabilities {
+            freq_range: MHZ..=6_000 * MHZ,
+            // the firmware takes any frequency whose MHz part is at most 7250
+            tuning_range: 0..=7_251 * MHZ - 1,
+            lna_gain_max: 40,
+            vga_gain_max: 62,
+            txvga_gain_max: 47,
+            amp: true,
+            bias_tee: true,
+            clkin: true,
+            clkout: true,
+        };
+
+        match self {
+            BoardKind::Jellybean | BoardKind::Jawbreaker => Capabilities {
+                freq_range: 10 * MHZ..=6_000 * MHZ,
+                bias_tee: false,
+                clkin: false,
+                clkout: false,
+                ..hackrf_one
+            },
+            BoardKind::HackRFOne | BoardKind::HackRFOneR9 | BoardKind::Praline => hackrf_one,
+            BoardKind::Rad1o => Capabilities {
+                freq_range: 50 * MHZ..=4_000 * MHZ,
+                bias_tee: false,
+                clkin: false,
+                clkout: false,
+                ..hackrf_one
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for BoardKind {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name: &str = match self {
+            BoardKind::Jellybean => "Jellybean",
+            BoardKind::Jawbreaker => "Jawbreaker",
+            BoardKind::HackRFOne => "HackRF One",
+            BoardKind::HackRFOneR9 => "HackRF One r9",
+            BoardKind::Rad1o => "rad1o",
+            BoardKind::Praline => "HackRF Pro",
+        };
+        write!(formatter, "{}", name)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BoardId {
+    Jellybean,
+    Jawbreaker,
+    HackRFOneOg,
+    Rad1o,
+    HackRFOneR9,
+    Praline,
+    Unrecognized(u8),
+}
+
+impl BoardId {
+    pub fn kind(&self) -> Option<BoardKind> {
+        match self {
+            BoardId::Jellybean => Some(BoardKind::Jellybean),
+            BoardId::Jawbreaker => Some(BoardKind::Jawbreaker),
+            BoardId::HackRFOneOg => Some(BoardKind::HackRFOne),
+            BoardId::Rad1o => Some(BoardKind::Rad1o),
+            BoardId::HackRFOneR9 => Some(BoardKind::HackRFOneR9),
+            BoardId::Praline => Some(BoardKind::Praline),
+            BoardId::Unrecognized(_) => None,
+        }
+    }
+}
+
+impl From<u8> for BoardId {
+    fn from(id: u8) -> Self {
+        match id {
+            0 => BoardId::Jellybean,
+            1 => BoardId::Jawbreaker,
+            2 => BoardId::HackRFOneOg,
+            3 => BoardId::Rad1o,
+            4 => BoardId::HackRFOneR9,
+            5 => BoardId::Praline,
+            id => BoardId::Unrecognized(id),
+        }
+    }
+}
+
+impl From<BoardId> for u8 {
+    fn from(id: BoardId) -> Self {
+        match id {
+            BoardId::Jellybean => 0,
+            BoardId::Jawbreaker => 1,
+            BoardId::HackRFOneOg => 2,
+            BoardId::Rad1o => 3,
+            BoardId::HackRFOneR9 => 4,
+            BoardId::Praline => 5,
+            BoardId::Unrecognized(id) => id,
+        }
+    }
+}
+
+impl std::fmt::Display for BoardId {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardId::Jellybean => write!(formatter, "Jellybean"),
+            BoardId::Jawbreaker => write!(formatter, "Jawbreaker"),
+            BoardId::HackRFOneOg => write!(formatter, "HackRF One"),
+            BoardId::Rad1o => write!(formatter, "rad1o"),
+            BoardId::HackRFOneR9 => write!(formatter, "HackRF One r9"),
+            BoardId::Praline => write!(formatter, "HackRF Pro"),
+            BoardId::Unrecognized(id) => write!(formatter, "unrecognized ({})", id),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BoardRevision {
+    Old,
+    R6,
+    R7,
+    R8,
+    R9,
+    R10,
+    GsgR6,
+    GsgR7,
+    GsgR8,
+    GsgR9,
+    GsgR10,
+    Unrecognized(u8),
+}
+
+impl BoardRevision {
+    // revisions with the high bit set were manufactured by Great Scott Gadgets
+    pub fn is_gsg(&self) -> bool {
+        matches!(
+            self,
+            BoardRevision::GsgR6
+                | BoardRevision::GsgR7
+                | BoardRevision::GsgR8
+                | BoardRevision::GsgR9
+                | BoardRevision::GsgR10
+        )
+    }
+}
+
+impl From<u8> for BoardRevision {
+    fn from(rev: u8) -> Self {
+        match rev {
+            0x00 => BoardRevision::Old,
+            0x01 => BoardRevision::R6,
+            0x02 => BoardRevision::R7,
+            0x03 => BoardRevision::R8,
+            0x04 => BoardRevision::R9,
+            0x05 => BoardRevision::R10,
+            0x81 => BoardRevision::GsgR6,
+            0x82 => BoardRevision::GsgR7,
+            0x83 => BoardRevision::GsgR8,
+            0x84 => BoardRevision::GsgR9,
+            0x85 => BoardRevision::GsgR10,
+            rev => BoardRevision::Unrecognized(rev),
+        }
+    }
+}
+
+impl std::fmt::Display for BoardRevision {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name: &str = match self {
+            BoardRevision::Old => "older than r6",
+            BoardRevision::R6 | BoardRevision::GsgR6 => "r6",
+            BoardRevision::R7 | BoardRevision::GsgR7 => "r7",
+            BoardRevision::R8 | BoardRevision::GsgR8 => "r8",
+            BoardRevision::R9 | BoardRevision::GsgR9 => "r9",
+            BoardRevision::R10 | BoardRevision::GsgR10 => "r10",
+            BoardRevision::Unrecognized(rev) => return write!(formatter, "unrecognized ({})", rev),
+        };
+        write!(formatter, "{}", name)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PlatformInfo {
+    bits: u32,
+}
+
+impl PlatformInfo {
+    const JAWBREAKER: u32 = 1 << 0;
+    const HACKRF1_OG: u32 = 1 << 1;
+    const RAD1O: u32 = 1 << 2;
+    const HACKRF1_R9: u32 = 1 << 3;
+    const PRALINE: u32 = 1 << 4;
+
+    pub fn from_bits(bits: u32) -> PlatformInfo {
+        PlatformInfo { bits }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn supports(&self, kind: BoardKind) -> bool {
+        let bit: u32 = match kind {
+            BoardKind::Jellybean => return false,
+            BoardKind::Jawbreaker => Self::JAWBREAKER,
+            BoardKind::HackRFOne => Self::HACKRF1_OG,
+            BoardKind::Rad1o => Self::RAD1O,
+            BoardKind::HackRFOneR9 => Self::HACKRF1_R9,
+            BoardKind::Praline => Self::PRALINE,
+        };
+        self.bits & bit != 0
+    }
+}
+
+impl std::fmt::Display for PlatformInfo {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = [
+            BoardKind::Jawbreaker,
+            BoardKind::HackRFOne,
+            BoardKind::Rad1o,
+            BoardKind::HackRFOneR9,
+            BoardKind::Praline,
+        ]
+        .iter()
+        .filter(|kind| self.supports(**kind))
+        .map(|kind| kind.to_string())
+        .collect();
+        write!(formatter, "{}", names.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub board_id: BoardId,
+    pub board_rev: Option<BoardRevision>,
+    pub supported_platform: Option<PlatformInfo>,
+    pub part_id: [u32; 2],
+    pub serial_number: SerialNumber,
+}
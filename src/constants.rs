@@ -1,10 +1,16 @@
 use rusb::constants::{LIBUSB_ENDPOINT_IN, LIBUSB_ENDPOINT_OUT};
 
 pub const HACKRF_USB_VID: u16 = 0x1D50;
+pub const HACKRF_JAWBREAKER_USB_PID: u16 = 0x604B;
 pub const HACKRF_ONE_USB_PID: u16 = 0x6089;
+pub const RAD1O_USB_PID: u16 = 0xCC15;
+// a HackRF One running the LPC43xx ROM bootloader, waiting for DFU
+pub const HACKRF_DFU_USB_PID: u16 = 0x600B;
 pub const RX_ENDPOINT_ADDRESS: u8 = LIBUSB_ENDPOINT_IN | 1;
 pub const TX_ENDPOINT_ADDRESS: u8 = LIBUSB_ENDPOINT_OUT | 2;
 pub const MAX_TRANSMISSION_UNIT: usize = 128 * 1024;
 pub const MHZ: u64 = 1_000_000;
-// pub const TRANSFER_SIZE: usize = 262_144;
-pub const MAX_N: usize = 32;
\ No newline at end of file
+pub const TRANSFER_SIZE: usize = 262_144;
+pub const TRANSFER_COUNT: usize = 4;
+pub const USB_MAX_PACKET_SIZE: usize = 512;
+pub const MAX_N: usize = 32;
@@ -0,0 +1,47 @@
+use crate::{Error, HackRF, SerialNumber};
+use std::time::{Duration, Instant};
+
+pub(crate) const CPLD_CHUNK_SIZE: usize = 512;
+// each chunk can carry an erase or program step, which takes a while
+pub(crate) const CPLD_CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
+
+const REOPEN_INTERVAL: Duration = Duration::from_millis(250);
+
+// Returned by `HackRF::cpld_update`. The firmware stops answering once the
+// bitstream is programmed, so the checksum can only be compared after the
+// board has been reset (button or replug) and opened again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpldUpdate {
+    pub serial_number: SerialNumber,
+    pub previous_checksum: u32,
+}
+
+impl CpldUpdate {
+    pub fn changed(&self, checksum: u32) -> bool {
+        checksum != self.previous_checksum
+    }
+
+    // Succeeds when the reopened board reports the checksum of the new bitstream.
+    pub fn verify(&self, hackrf: &HackRF, expected: u32) -> Result<(), Error> {
+        if hackrf.cpld_checksum()? == expected {
+            Ok(())
+        } else {
+            Err(Error::Verification)
+        }
+    }
+
+    // Waits for the board to come back after a reset.
+    pub fn reopen(&self, timeout: Duration) -> Result<HackRF, Error> {
+        let deadline: Instant = Instant::now() + timeout;
+        let serial_number: String = self.serial_number.to_string();
+
+        loop {
+            match HackRF::open_by_serial(&serial_number) {
+                Err(Error::NotFound) if Instant::now() < deadline => {
+                    std::thread::sleep(REOPEN_INTERVAL)
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::constants::HACKRF_DFU_USB_PID;
+use crate::{BoardId, BoardKind, Error, SerialNumber};
+use rusb::Version;
+
+// One HackRF-family board found on the bus. Boards that could not be opened
+// or queried (no permission, claimed elsewhere, in DFU mode) are still
+// listed, with the reason in `details`.
+#[derive(Debug, Clone)]
+pub struct DeviceListEntry {
+    pub bus_number: u8,
+    pub port_numbers: Vec<u8>,
+    pub address: u8,
+    pub product_id: u16,
+    pub details: Result<DeviceDetails, Error>,
+}
+
+// What the board reported about itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceDetails {
+    pub board_kind: BoardKind,
+    pub serial_number: SerialNumber,
+    pub board_id: BoardId,
+    pub firmware_version: String,
+    pub api_version: Version,
+}
+
+impl DeviceListEntry {
+    // waiting in the ROM bootloader for a firmware download; `details` is
+    // `Error::Unsupported`
+    pub fn in_dfu_mode(&self) -> bool {
+        self.product_id == HACKRF_DFU_USB_PID
+    }
+
+    pub fn bus_path(&self) -> String {
+        let ports: Vec<String> = self.port_numbers.iter().map(|p| p.to_string()).collect();
+        format!("{}-{}", self.bus_number, ports.join("."))
+    }
+}
@@ -1,17 +1,19 @@
-use libhackrf::HackRF;
+use libhackrf::{DeviceInfo, HackRF};
 
 fn main() {
     let sdr: HackRF = HackRF::new().expect("Failed to open HackRF One");
-    println!("Board id: {:}", sdr.board_id().unwrap());
+    let info: DeviceInfo = sdr.device_info().unwrap();
+    println!("Board id: {:}", info.board_id);
+    if let Some(board_rev) = info.board_rev {
+        println!("Hardware revision: {:}", board_rev);
+    }
+    if let Some(platform) = info.supported_platform {
+        println!("Hardware supported by installed firmware: {:}", platform);
+    }
     println!("Firmware version: {:}", sdr.version().unwrap());
-    println!("API version: {:}", sdr.device_version().to_string());
-
-    let part_and_serial: ((u32, u32), String) = sdr.part_id_serial_read().unwrap();
+    println!("API version: {:}", sdr.device_version());
     println!(
-        "{}",
-        format!(
-            "Part id number: 0x{:08x?} 0x{:08x?}\nSerial number: {:032x?}",
-            part_and_serial.0 .0, part_and_serial.0 .1, part_and_serial.1,
-        )
+        "Part id number: 0x{:08x} 0x{:08x}\nSerial number: {:}",
+        info.part_id[0], info.part_id[1], info.serial_number,
     );
 }
@@ -1,4 +1,4 @@
-use libhackrf::HackRF;
+use libhackrf::{Complex, HackRF, IqBlock, IqBlocks, Receiver, StreamConfig};
 use std::{
     sync::mpsc::{channel, TryRecvError},
     thread,
@@ -51,10 +51,10 @@ fn receive(args: Args) {
     sdr.set_vga_gain(args.vga_gain)
         .expect("Failed to set VGA gain");
 
-    sdr.enter_rx_mode().expect("Failed to enter RX mode");
+    let mut receiver: Receiver = sdr.enter_rx_mode().expect("Failed to enter RX mode");
 
     const RECORD_BUFFER_SIZE: usize = 1024 * 1024;
-    let mut record_buffer: Vec<[u8; 2]> = Vec::with_capacity(RECORD_BUFFER_SIZE);
+    let mut record_buffer: Vec<Complex<i8>> = Vec::with_capacity(RECORD_BUFFER_SIZE);
 
     let (samples_sender, samples_receiver) = channel();
     let (exit_tx, exit_rx) = channel();
@@ -63,15 +63,17 @@ fn receive(args: Args) {
         thread::spawn(move || -> Result<(), libhackrf::Error> {
             println!("Sample thread has been spawned");
 
+            let mut blocks: IqBlocks = receiver.rx_stream(StreamConfig::default())?.iq_blocks();
             loop {
-                let samples: Vec<u8> = sdr.rx()?;
+                let block: IqBlock = blocks.next().expect("RX stream ended")?;
                 samples_sender
-                    .send(samples)
+                    .send(block)
                     .expect("Failed to send buffer data from sample thread");
 
                 match exit_rx.try_recv() {
                     Ok(_) => {
-                        sdr.stop_rx()?;
+                        drop(blocks);
+                        receiver.stop().map_err(|(_, e)| e)?;
                         return Ok(());
                     }
                     Err(TryRecvError::Disconnected) => {
@@ -86,11 +88,9 @@ fn receive(args: Args) {
     let mut i: i32 = 0;
     loop {
         match samples_receiver.try_recv() {
-            Ok(buffer) => {
-                buffer.chunks_exact(2).for_each(|iq: &[u8]| {
-                    record_buffer.push([iq[0], iq[1]]);
-                });
-                println!("{}", buffer.len());
+            Ok(block) => {
+                record_buffer.extend_from_slice(&block.samples);
+                println!("{} samples at offset {}", block.samples.len(), block.offset);
                 thread::sleep(Duration::from_secs(1));
                 i += 1;
                 println!("RX time: {} s.", i);
@@ -1,5 +1,5 @@
 pub mod args;
-use libhackrf::HackRF;
+use libhackrf::{HackRF, Receiver, RxStream, RxTransfer, StreamConfig, StreamStats};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{
@@ -41,7 +41,7 @@ fn receive(args: args::Args) {
 
     sdr.set_vga_gain(args.vga).expect("Failed to set VGA gain");
 
-    sdr.enter_rx_mode().expect("Failed to enter RX mode");
+    let mut receiver: Receiver = sdr.enter_rx_mode().expect("Failed to enter RX mode");
 
     let mut write_dir: PathBuf = env::current_dir().unwrap();
     write_dir.push(args.file_name);
@@ -55,17 +55,23 @@ fn receive(args: args::Args) {
     let sample_thread: thread::JoinHandle<Result<(), libhackrf::Error>> =
         thread::spawn(move || -> Result<(), libhackrf::Error> {
             println!("Sample thread has been spawned");
-            loop {
-                let samples: Vec<u8> = sdr.rx()?;
-                file.write_all(&samples).expect("Failed to write to file");
+            let mut stream: RxStream = receiver.rx_stream(StreamConfig::default())?;
+            let stats: StreamStats = stream.for_each_transfer(|transfer: RxTransfer| {
+                file.write_all(transfer.data)
+                    .expect("Failed to write to file");
                 status_tx
                     .send(())
                     .expect("Failed to send status from sample thread");
-                if exit_flag_clone.load(Ordering::Relaxed) {
-                    sdr.stop_rx().unwrap();
-                    return Ok(());
-                }
-            }
+                !exit_flag_clone.load(Ordering::Relaxed)
+            })?;
+            drop(stream);
+
+            println!(
+                "Received {} bytes, lost transfers: {}, short transfers: {}",
+                stats.bytes, stats.lost_transfers, stats.short_transfers
+            );
+            receiver.stop().map_err(|(_, e)| e)?;
+            Ok(())
         });
 
     let exit_flag_clone = exit_flag.clone();
@@ -1,4 +1,4 @@
-use libhackrf::{HackRF, MAX_TRANSMISSION_UNIT};
+use libhackrf::{HackRF, StreamConfig, StreamStats, Transmitter, TxStream};
 use rand::{distributions::Uniform, Rng};
 use std::{
     sync::mpsc::{channel, TryRecvError},
@@ -60,7 +60,7 @@ fn transmit(args: Args) {
         .set_txvga_gain(args.txvga_gain)
         .expect("Failed to set VGA gain");
 
-    hackrf.enter_tx_mode().expect("Failed to enter TX mode");
+    let mut transmitter: Transmitter = hackrf.enter_tx_mode().expect("Failed to enter TX mode");
     let (exit_tx, exit_rx) = channel();
 
     let sample_thread: thread::JoinHandle<Result<(), libhackrf::Error>> =
@@ -69,24 +69,31 @@ fn transmit(args: Args) {
 
             println!("Spawned sample thread");
 
-            loop {
-                hackrf.tx(rand::thread_rng()
-                    .sample_iter(&range)
-                    .take(MAX_TRANSMISSION_UNIT)
-                    .collect())?;
-
+            let mut stream: TxStream = transmitter.tx_stream(StreamConfig::default())?;
+            let stats: StreamStats = stream.transmit(|buffer: &mut [u8]| {
                 match exit_rx.try_recv() {
-                    Ok(_) => {
-                        hackrf.stop_tx()?;
-                        return Ok(());
-                    }
+                    Ok(_) => return None,
                     Err(TryRecvError::Disconnected) => {
                         println!("Main thread disconnected");
-                        return Ok(());
+                        return None;
                     }
                     Err(TryRecvError::Empty) => {}
                 }
-            }
+
+                buffer
+                    .iter_mut()
+                    .zip(rand::thread_rng().sample_iter(&range))
+                    .for_each(|(sample, value)| *sample = value);
+                Some(buffer.len())
+            })?;
+            drop(stream);
+
+            println!(
+                "Transmitted {} bytes, underruns: {}",
+                stats.bytes, stats.underruns
+            );
+            transmitter.stop().map_err(|(_, e)| e)?;
+            Ok(())
         });
 
     for i in 1..=5 {
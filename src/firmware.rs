@@ -0,0 +1,39 @@
+use crate::spiflash::SPIFLASH_SIZE;
+use crate::{BoardKind, Error};
+use std::path::Path;
+
+// A firmware `.bin` as written to SPI flash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareImage {
+    data: Vec<u8>,
+}
+
+impl FirmwareImage {
+    pub fn from_bytes(data: Vec<u8>) -> Result<FirmwareImage, Error> {
+        if data.is_empty() || data.len() > SPIFLASH_SIZE {
+            return Err(Error::Argument);
+        }
+        Ok(FirmwareImage { data })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FirmwareImage, Error> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Images carry the name of the board they were built for; this is the
+    // same check hackrf_spiflash does before flashing.
+    pub fn targets(&self, kind: BoardKind) -> bool {
+        let name: &[u8] = match kind {
+            BoardKind::Jellybean => b"HackRF Jellybean",
+            BoardKind::Jawbreaker => b"HackRF Jawbreaker",
+            BoardKind::HackRFOne | BoardKind::HackRFOneR9 => b"HackRF One",
+            BoardKind::Rad1o => b"rad1o",
+            BoardKind::Praline => b"HackRF Pro",
+        };
+        self.data.windows(name.len()).any(|window| window == name)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::operacake::OperacakeSegment;
+use crate::stream::RxStream;
+use crate::transfer::TransferStatus;
+use crate::Error;
+use num_complex::Complex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IqBlock {
+    // index of the first sample since the stream started; a jump larger than
+    // the previous block's length means samples were lost in between
+    pub offset: u64,
+    // samples may have been dropped on the board just before this block
+    // without moving `offset`, see `RxTransfer::overrun`
+    pub overrun: bool,
+    pub samples: Vec<Complex<i8>>,
+    // the Opera Cake port each run of samples came from when a time-mode
+    // schedule was loaded, counted as if switching began with the stream's
+    // first sample; empty otherwise
+    pub ports: Vec<OperacakeSegment>,
+}
+
+impl IqBlock {
+    pub(crate) fn from_bytes(offset: u64, data: &[u8]) -> IqBlock {
+        IqBlock {
+            offset: offset / 2,
+            overrun: false,
+            samples: data
+                .chunks_exact(2)
+                .map(|iq: &[u8]| Complex::new(iq[0] as i8, iq[1] as i8))
+                .collect(),
+            ports: Vec::new(),
+        }
+    }
+}
+
+pub struct IqBlocks<'a> {
+    stream: RxStream<'a>,
+    failed: bool,
+}
+
+impl<'a> IqBlocks<'a> {
+    pub(crate) fn new(stream: RxStream<'a>) -> Self {
+        IqBlocks {
+            stream,
+            failed: false,
+        }
+    }
+
+    pub fn into_inner(self) -> RxStream<'a> {
+        self.stream
+    }
+}
+
+impl Iterator for IqBlocks<'_> {
+    type Item = Result<IqBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            match self.stream.next_transfer() {
+                Ok(transfer) if transfer.status == TransferStatus::Lost => continue,
+                Ok(transfer) => {
+                    let mut block: IqBlock = IqBlock::from_bytes(transfer.offset, transfer.data);
+                    block.overrun = transfer.overrun;
+                    if let Some(schedule) = self.stream.operacake_schedule() {
+                        block.ports = schedule.segments(block.offset, block.samples.len());
+                    }
+                    return Some(Ok(block));
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use self::async_stream::IqStream;
+
+#[cfg(feature = "async")]
+mod async_stream {
+    use super::IqBlock;
+    use crate::stream::StreamConfig;
+    use crate::{Error, Receiver};
+    use futures::channel::mpsc;
+    use futures::{executor, SinkExt, Stream, StreamExt};
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::thread;
+
+    // blocks buffered between the USB thread and the consumer
+    const CHANNEL_CAPACITY: usize = 16;
+
+    // The libusb transfers are driven by a thread that owns the device; the
+    // stream hands its blocks to async code and gives the receiver back on stop.
+    pub struct IqStream {
+        receiver: mpsc::Receiver<Result<IqBlock, Error>>,
+        exit: Arc<AtomicBool>,
+        thread: Option<thread::JoinHandle<Receiver>>,
+    }
+
+    impl IqStream {
+        pub(crate) fn spawn(mut receiver: Receiver, config: StreamConfig) -> IqStream {
+            let (mut sender, blocks_receiver) = mpsc::channel(CHANNEL_CAPACITY);
+            let exit: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+            let exit_flag: Arc<AtomicBool> = exit.clone();
+
+            let thread: thread::JoinHandle<Receiver> = thread::spawn(move || {
+                let blocks: Box<dyn Iterator<Item = Result<IqBlock, Error>>> =
+                    match receiver.rx_stream(config) {
+                        Ok(stream) => Box::new(stream.iq_blocks()),
+                        Err(e) => Box::new(std::iter::once(Err(e))),
+                    };
+
+                for block in blocks {
+                    let failed: bool = block.is_err();
+                    if exit_flag.load(Ordering::Relaxed)
+                        || executor::block_on(sender.send(block)).is_err()
+                        || failed
+                    {
+                        break;
+                    }
+                }
+                receiver
+            });
+
+            IqStream {
+                receiver: blocks_receiver,
+                exit,
+                thread: Some(thread),
+            }
+        }
+
+        // A panic in the stream thread is passed on to the caller here.
+        pub fn stop(mut self) -> Receiver {
+            match self.join().expect("IQ stream thread vanished") {
+                Ok(receiver) => receiver,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+
+        fn join(&mut self) -> Option<thread::Result<Receiver>> {
+            self.exit.store(true, Ordering::Relaxed);
+            self.receiver.close();
+            self.thread.take().map(|thread| thread.join())
+        }
+    }
+
+    impl Stream for IqStream {
+        type Item = Result<IqBlock, Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.receiver.poll_next_unpin(cx)
+        }
+    }
+
+    impl Drop for IqStream {
+        fn drop(&mut self) {
+            if let Some(Err(_)) = self.join() {
+                log::warn!("IQ stream thread panicked");
+            }
+        }
+    }
+}
@@ -1,71 +1,262 @@
+mod board;
 mod constants;
+mod cpld;
+mod device_list;
+mod firmware;
+mod iq;
+mod m0_state;
+mod max2837;
+mod mock;
+mod operacake;
+mod recording;
 mod request;
+mod rffc5071;
+mod serial_number;
+mod si5351c;
+mod simulator;
+mod spiflash;
+mod stream;
+mod sweep;
+mod sync_group;
 mod tests;
+mod transceiver;
 mod transceiver_mode;
+mod transfer;
+mod transport;
+mod tuning;
 
 use constants::*;
-use request::*;
-use transceiver_mode::*;
+use cpld::*;
+use m0_state::M0_STATE_SIZE;
+use max2837::*;
+use operacake::*;
+use rffc5071::RFFC5071_REGISTER_COUNT;
+use si5351c::SI5351C_REGISTER_COUNT;
+use spiflash::*;
+use sweep::{MAX_SWEEP_RANGES, SWEEP_BLOCK_SIZE};
+
+pub use board::{BoardId, BoardKind, BoardRevision, Capabilities, DeviceInfo, PlatformInfo};
+pub use cpld::CpldUpdate;
+pub use device_list::{DeviceDetails, DeviceListEntry};
+pub use firmware::FirmwareImage;
+#[cfg(feature = "async")]
+pub use iq::IqStream;
+pub use iq::{IqBlock, IqBlocks};
+pub use m0_state::M0State;
+pub use max2837::Max2837Registers;
+pub use mock::{MockRecord, MockTransport};
+pub use num_complex::Complex;
+pub use operacake::{
+    OperacakeGpioTest, OperacakeMode, OperacakePin, OperacakePort, OperacakeSchedule,
+    OperacakeSegment,
+};
+pub use recording::{RecordingTransport, ReplayTransport};
+pub use request::Request;
+pub use rffc5071::{Rffc5071Path, Rffc5071Registers, Rffc5071Synth};
+pub use serial_number::{PartIdSerial, SerialNumber};
+pub use si5351c::{ClockControl, ClockSource, Multisynth, Pll, Si5351cRegisters, Si5351cStatus};
+pub use simulator::{Signal, SimulatedHackRF};
+pub use spiflash::SpiflashStatus;
+pub use stream::{RxStream, RxTransfer, StreamConfig, StreamStats, TxStream};
+pub use sweep::{SweepBlock, SweepBlocks, SweepStyle};
+pub use sync_group::{GroupBlock, SyncConfig, SyncGroup, SyncStream};
+pub use transceiver::{Receiver, Sweeper, Transmitter};
+pub use transceiver_mode::{HwSyncMode, TransceiverMode};
+pub use transfer::TransferStatus;
+pub use transport::{BulkStream, Transport, UsbTransport};
+pub use tuning::{FreqPlan, RfPathFilter};
 
 pub const MAX_TRANSMISSION_UNIT: usize = constants::MAX_TRANSMISSION_UNIT;
 
-use rusb::{
-    request_type, DeviceDescriptor, DeviceHandle, Direction, GlobalContext, Recipient, RequestType,
-    UsbContext, Version,
-};
+use rusb::{Device, DeviceDescriptor, Direction, GlobalContext, UsbContext, Version};
 use std::time::Duration;
 
-#[derive(Debug)]
-pub enum Mode {
-    Off,
-    Tx,
-    Rx,
-}
-
 #[derive(Debug)]
 pub struct HackRF {
-    device_handle: DeviceHandle<GlobalContext>,
-    description: DeviceDescriptor,
+    transport: Box<dyn Transport>,
+    kind: BoardKind,
 
-    mode: Mode,
     timeout: Duration,
+    // rate set by `set_sample_rate`, unknown until then
+    sample_rate: Option<f64>,
+    // tuning offset of the sweep set up by `init_sweep`
+    sweep_offset: Option<u32>,
+    // dwell times loaded with `operacake_set_dwell_times`
+    operacake_schedule: Option<OperacakeSchedule>,
+    // boards put into `OperacakeMode::Time`, one bit per address
+    operacake_time_boards: u8,
+
+    // what `Drop` has to undo
+    active: bool,
+    claimed: bool,
+    amp_enabled: bool,
+    antenna_enabled: bool,
+    // hardware sync switched on by `enter_armed_rx_mode`
+    hw_sync_armed: bool,
 }
 
 impl HackRF {
-    //TODO:
-    //refactor to "pub fn new (serial_num: String) -> Option<HackRF> {...}"
-    //implement method "pub fn list_devices() -> vec<String>{...}"
-
     pub fn new() -> Option<HackRF> {
+        let devices = Self::usb_devices().ok()?;
+
+        for (device, description) in devices {
+            if let Ok(hackrf) = Self::open(&device, description) {
+                return Some(hackrf);
+            }
+        }
+        None
+    }
+
+    pub fn list_devices() -> Result<Vec<DeviceListEntry>, Error> {
+        let mut entries: Vec<DeviceListEntry> = Vec::new();
+
+        for (device, description) in Self::usb_devices()? {
+            entries.push(DeviceListEntry {
+                bus_number: device.bus_number(),
+                port_numbers: device.port_numbers().unwrap_or_default(),
+                address: device.address(),
+                product_id: description.product_id(),
+                details: Self::device_details(&device, description),
+            });
+        }
+        Ok(entries)
+    }
+
+    // Only control transfers are issued here, interface 0 is never claimed,
+    // so a device streaming in another process is left untouched.
+    fn device_details(
+        device: &Device<GlobalContext>,
+        description: DeviceDescriptor,
+    ) -> Result<DeviceDetails, Error> {
+        let hackrf: HackRF = Self::open(device, description).map_err(Self::open_error)?;
+        Ok(DeviceDetails {
+            board_id: hackrf.board_id()?,
+            firmware_version: hackrf.version()?,
+            serial_number: hackrf.part_id_serial_read()?.serial,
+            board_kind: hackrf.board_kind(),
+            api_version: hackrf.device_version(),
+        })
+    }
+
+    pub fn open_by_serial(serial_number: &str) -> Result<HackRF, Error> {
+        let mut error: Error = Error::NotFound;
+
+        for (device, description) in Self::usb_devices()? {
+            let hackrf: HackRF = match Self::open(&device, description) {
+                Ok(hackrf) => hackrf,
+                Err(e) => {
+                    // a device we could not open may be the one asked for,
+                    // so report why rather than a plain "not found"
+                    error = Self::open_error(e);
+                    continue;
+                }
+            };
+            match hackrf.part_id_serial_read() {
+                Ok(part_id_serial) if part_id_serial.serial.matches(serial_number) => {
+                    hackrf.probe_interface()?;
+                    return Ok(hackrf);
+                }
+                _ => continue,
+            }
+        }
+        Err(error)
+    }
+
+    pub fn open_by_index(index: usize) -> Result<HackRF, Error> {
+        let (device, description) = Self::usb_devices()?
+            .into_iter()
+            .nth(index)
+            .ok_or(Error::NotFound)?;
+
+        let hackrf: HackRF = Self::open(&device, description).map_err(Self::open_error)?;
+        hackrf.probe_interface()?;
+        Ok(hackrf)
+    }
+
+    pub fn open_by_bus_address(bus_number: u8, address: u8) -> Result<HackRF, Error> {
+        let (device, description) = Self::usb_devices()?
+            .into_iter()
+            .find(|(device, _)| device.bus_number() == bus_number && device.address() == address)
+            .ok_or(Error::NotFound)?;
+
+        let hackrf: HackRF = Self::open(&device, description).map_err(Self::open_error)?;
+        hackrf.probe_interface()?;
+        Ok(hackrf)
+    }
+
+    fn probe_interface(&self) -> Result<(), Error> {
+        self.transport
+            .claim_interface(0)
+            .map_err(Self::open_error)?;
+        self.transport.release_interface(0)?;
+        Ok(())
+    }
+
+    fn open_error(error: Error) -> Error {
+        match error {
+            Error::Usb(rusb::Error::Access) => Error::PermissionDenied,
+            Error::Usb(rusb::Error::Busy) => Error::Busy,
+            Error::Usb(rusb::Error::NoDevice) | Error::Usb(rusb::Error::NotFound) => {
+                Error::NotFound
+            }
+            error => error,
+        }
+    }
+
+    fn usb_devices() -> Result<Vec<(Device<GlobalContext>, DeviceDescriptor)>, Error> {
         let context: GlobalContext = GlobalContext {};
-        let devices = match context.devices() {
-            Ok(dev) => dev,
-            Err(_) => return None,
-        };
+        let mut devices = Vec::new();
 
-        for device in devices.iter() {
+        for device in context.devices()?.iter() {
             let description = match device.device_descriptor() {
                 Ok(dev) => dev,
                 Err(_) => continue,
             };
 
             if description.vendor_id() == HACKRF_USB_VID
-                && description.product_id() == HACKRF_ONE_USB_PID
+                && (BoardKind::from_usb_pid(description.product_id()).is_some()
+                    || description.product_id() == HACKRF_DFU_USB_PID)
             {
-                match device.open() {
-                    Ok(handle) => {
-                        return Some(HackRF {
-                            device_handle: handle,
-                            description,
-                            mode: Mode::Off,
-                            timeout: Duration::from_secs(1),
-                        })
-                    }
-                    Err(_) => continue,
-                }
+                devices.push((device, description));
             }
         }
-        None
+        Ok(devices)
+    }
+
+    fn open(
+        device: &Device<GlobalContext>,
+        description: DeviceDescriptor,
+    ) -> Result<HackRF, Error> {
+        // the ROM bootloader answers none of the vendor requests
+        if description.product_id() == HACKRF_DFU_USB_PID {
+            return Err(Error::Unsupported);
+        }
+        Self::with_transport(UsbTransport::new(device.open()?, description))
+    }
+
+    // Drives the board through any transport, e.g. a mock in tests.
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> Result<HackRF, Error> {
+        let kind: BoardKind =
+            BoardKind::from_usb_pid(transport.product_id()).ok_or(Error::NotFound)?;
+        let mut hackrf: HackRF = HackRF {
+            transport: Box::new(transport),
+            kind,
+            timeout: Duration::from_secs(1),
+            sample_rate: None,
+            sweep_offset: None,
+            operacake_schedule: None,
+            operacake_time_boards: 0,
+            active: false,
+            claimed: false,
+            amp_enabled: false,
+            antenna_enabled: false,
+            hw_sync_armed: false,
+        };
+
+        if let Some(kind) = hackrf.board_id().ok().and_then(|id| id.kind()) {
+            hackrf.kind = kind;
+        }
+        Ok(hackrf)
     }
 
     fn read_control<const N: usize>(
@@ -76,14 +267,9 @@ impl HackRF {
     ) -> Result<[u8; N], Error> {
         let mut buffer: [u8; N] = [0; N];
 
-        let n: usize = self.device_handle.read_control(
-            request_type(Direction::In, RequestType::Vendor, Recipient::Device),
-            request.into(),
-            value,
-            index,
-            &mut buffer,
-            self.timeout,
-        )?;
+        let n: usize =
+            self.transport
+                .read_control(request, value, index, &mut buffer, self.timeout)?;
 
         if n != buffer.len() {
             Err(Error::ControlTransfer {
@@ -103,14 +289,9 @@ impl HackRF {
         index: u16,
         buffer: &[u8],
     ) -> Result<(), Error> {
-        let n: usize = self.device_handle.write_control(
-            request_type(Direction::Out, RequestType::Vendor, Recipient::Device),
-            request.into(),
-            value,
-            index,
-            buffer,
-            self.timeout,
-        )?;
+        let n: usize = self
+            .transport
+            .write_control(request, value, index, buffer, self.timeout)?;
         if n != buffer.len() {
             Err(Error::ControlTransfer {
                 direction: Direction::Out,
@@ -143,48 +324,67 @@ impl HackRF {
         }
     }
     pub fn device_version(&self) -> Version {
-        self.description.device_version()
+        self.transport.device_version()
     }
 
     pub fn set_timeout(&mut self, duration: Duration) {
         self.timeout = duration;
     }
 
-    pub fn board_id(&self) -> Result<u8, Error> {
+    pub fn board_id(&self) -> Result<BoardId, Error> {
         let data: [u8; 1] = self.read_control(Request::BoardIdRead, 0, 0)?;
-        Ok(data[0])
+        Ok(data[0].into())
     }
 
-    pub fn part_id_serial_read(self) -> Result<((u32, u32), String), Error> {
-        let mut buffer: [u8; 32] = [0; 32];
-        self.device_handle.read_control(
-            request_type(Direction::In, RequestType::Vendor, Recipient::Device),
-            Request::BoardPartidSerialnoRead.into(),
-            0,
-            0,
-            &mut buffer,
-            self.timeout,
-        )?;
-        let part_id_1: u32 = u32::from_le_bytes(buffer[0..4].try_into().unwrap());
-        let part_id_2: u32 = u32::from_le_bytes(buffer[4..8].try_into().unwrap());
+    pub fn board_rev(&self) -> Result<BoardRevision, Error> {
+        self.check_api_version(Version::from_bcd(0x0106))?;
+        let data: [u8; 1] = self.read_control(Request::BoardRevRead, 0, 0)?;
+        Ok(data[0].into())
+    }
 
-        let mut serial_number: String = "".to_owned();
+    pub fn supported_platform(&self) -> Result<PlatformInfo, Error> {
+        self.check_api_version(Version::from_bcd(0x0106))?;
+        let data: [u8; 4] = self.read_control(Request::SupportedPlatformRead, 0, 0)?;
+        Ok(PlatformInfo::from_bits(u32::from_be_bytes(data)))
+    }
 
-        for i in 0..4 {
-            serial_number += &format!(
-                "{:08x?}",
-                u32::from_le_bytes(buffer[8 + 4 * i..12 + 4 * i].try_into().unwrap())
-            );
+    pub fn device_info(&self) -> Result<DeviceInfo, Error> {
+        // revision and platform queries only exist on newer firmware
+        fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+            match result {
+                Ok(value) => Ok(Some(value)),
+                Err(Error::Version { .. }) => Ok(None),
+                Err(e) => Err(e),
+            }
         }
 
-        Ok(((part_id_1, part_id_2), serial_number))
+        let part_id_serial: PartIdSerial = self.part_id_serial_read()?;
+        Ok(DeviceInfo {
+            board_id: self.board_id()?,
+            board_rev: optional(self.board_rev())?,
+            supported_platform: optional(self.supported_platform())?,
+            part_id: part_id_serial.part_id,
+            serial_number: part_id_serial.serial,
+        })
+    }
+
+    pub fn board_kind(&self) -> BoardKind {
+        self.kind
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.kind.capabilities()
+    }
+
+    pub fn part_id_serial_read(&self) -> Result<PartIdSerial, Error> {
+        let buffer: [u8; 24] = self.read_control(Request::BoardPartidSerialnoRead, 0, 0)?;
+        Ok(PartIdSerial::from_bytes(&buffer))
     }
 
     pub fn version(&self) -> Result<String, Error> {
         let mut buffer: [u8; 16] = [0; 16];
-        let n: usize = self.device_handle.read_control(
-            request_type(Direction::In, RequestType::Vendor, Recipient::Device),
-            Request::VersionStringRead.into(),
+        let n: usize = self.transport.read_control(
+            Request::VersionStringRead,
             0,
             0,
             &mut buffer,
@@ -194,12 +394,34 @@ impl HackRF {
     }
 
     pub fn set_freq(&mut self, hz: u64) -> Result<(), Error> {
-        let mut buffer: [u8; 8] = freq_params(hz);
-        self.write_control(Request::SetFreq, 0, 0, &mut buffer)
+        if !self.capabilities().tuning_range.contains(&hz) {
+            return Err(Error::Argument);
+        }
+        let buffer: [u8; 8] = freq_params(hz);
+        self.write_control(Request::SetFreq, 0, 0, &buffer)
+    }
+
+    // Tunes with a caller-chosen IF, LO and filter path instead of the plan
+    // `set_freq` would use, see `FreqPlan::for_freq`. The LO is ignored on
+    // the bypass path.
+    pub fn set_freq_explicit(
+        &mut self,
+        if_hz: u64,
+        lo_hz: u64,
+        path: RfPathFilter,
+    ) -> Result<(), Error> {
+        let plan: FreqPlan = FreqPlan { if_hz, lo_hz, path };
+        plan.validate()?;
+        self.write_control(Request::SetFreqExplicit, 0, 0, &plan.to_bytes())
     }
 
     pub fn set_amp_enable(&mut self, en: bool) -> Result<(), Error> {
-        self.write_control(Request::AmpEnable, en.into(), 0, &mut [])
+        if en && !self.capabilities().amp {
+            return Err(Error::Unsupported);
+        }
+        self.write_control(Request::AmpEnable, en.into(), 0, &[])?;
+        self.amp_enabled = en;
+        Ok(())
     }
 
     pub fn set_baseband_filter_bandwidth(&mut self, hz: u32) -> Result<(), Error> {
@@ -207,10 +429,14 @@ impl HackRF {
             Request::BasebandFilterBandwidthSet,
             (hz & 0xFFFF) as u16,
             (hz >> 16) as u16,
-            &mut [],
+            &[],
         )
     }
 
+    pub fn sample_rate(&self) -> Option<f64> {
+        self.sample_rate
+    }
+
     pub fn set_sample_rate_auto(&mut self, freq: u32) -> Result<(), Error> {
         // let freq_frac = 1.0 + freq - freq.trunc();
 
@@ -244,10 +470,13 @@ impl HackRF {
     }
 
     pub fn set_sample_rate(&mut self, hz: u32, divider: u32) -> Result<(), Error> {
+        if divider == 0 {
+            return Err(Error::Argument);
+        }
         let hz: u32 = hz.to_le();
         let div: u32 = divider.to_le();
 
-        let mut buffer: [u8; 8] = [
+        let buffer: [u8; 8] = [
             (hz & 0xFF) as u8,
             ((hz >> 8) & 0xFF) as u8,
             ((hz >> 16) & 0xFF) as u8,
@@ -258,12 +487,13 @@ impl HackRF {
             ((div >> 24) & 0xFF) as u8,
         ];
 
-        self.write_control(Request::SampleRateSet, 0, 0, &mut buffer)?;
+        self.write_control(Request::SampleRateSet, 0, 0, &buffer)?;
+        self.sample_rate = Some(f64::from(hz) / f64::from(divider));
         self.set_baseband_filter_bandwidth((0.75 * (hz as f32) / (div as f32)) as u32)
     }
 
     pub fn set_lna_gain(&mut self, value: u16) -> Result<(), Error> {
-        if value > 40 {
+        if value > self.capabilities().lna_gain_max {
             Err(Error::Argument)
         } else {
             let buffer: [u8; 1] = self.read_control(Request::SetLnaGain, 0, value & !0x07)?;
@@ -276,7 +506,7 @@ impl HackRF {
     }
 
     pub fn set_vga_gain(&mut self, value: u16) -> Result<(), Error> {
-        if value > 62 {
+        if value > self.capabilities().vga_gain_max {
             Err(Error::Argument)
         } else {
             let buffer: [u8; 1] = self.read_control(Request::SetVgaGain, 0, value & !0b1)?;
@@ -289,7 +519,7 @@ impl HackRF {
     }
 
     pub fn set_txvga_gain(&mut self, value: u16) -> Result<(), Error> {
-        if value > 47 {
+        if value > self.capabilities().txvga_gain_max {
             Err(Error::Argument)
         } else {
             let buffer: [u8; 1] = self.read_control(Request::SetTxvgaGain, 0, value)?;
@@ -302,76 +532,558 @@ impl HackRF {
     }
 
     pub fn set_antenna_enable(&mut self, value: u8) -> Result<(), Error> {
-        self.write_control(Request::AntennaEnable, value.into(), 0, &mut [])
+        if value != 0 && !self.capabilities().bias_tee {
+            return Err(Error::Unsupported);
+        }
+        self.write_control(Request::AntennaEnable, value.into(), 0, &[])?;
+        self.antenna_enabled = value != 0;
+        Ok(())
     }
 
     pub fn set_clkout_enable(&mut self, value: bool) -> Result<(), Error> {
+        if value && !self.capabilities().clkout {
+            return Err(Error::Unsupported);
+        }
         self.check_api_version(Version::from_bcd(0x0103))?;
-        self.write_control(Request::ClkoutEnable, value.into(), 0, &mut [])
+        self.write_control(Request::ClkoutEnable, value.into(), 0, &[])
     }
 
-    pub fn set_hw_sync_mode(&mut self, value: u8) -> Result<(), Error> {
-        self.write_control(Request::SetHwSyncMode, value.into(), 0, &mut [])
+    // whether a reference clock is present on CLKIN
+    pub fn clkin_status(&self) -> Result<bool, Error> {
+        self.check_api_version(Version::from_bcd(0x0106))?;
+        let buffer: [u8; 1] = self.read_control(Request::GetClkinStatus, 0, 0)?;
+        Ok(buffer[0] != 0)
     }
 
-    pub fn reset(mut self) -> Result<(), Error> {
-        self.check_api_version(Version::from_bcd(0x0102))?;
-        self.write_control(Request::Reset, 0, 0, &mut [])?;
-        self.mode = Mode::Off;
+    pub fn set_hw_sync_mode(&mut self, mode: HwSyncMode) -> Result<(), Error> {
+        self.write_control(Request::SetHwSyncMode, mode.into(), 0, &[])
+    }
+
+    pub fn m0_state(&self) -> Result<M0State, Error> {
+        self.check_api_version(Version::from_bcd(0x0106))?;
+        let buffer: [u8; M0_STATE_SIZE] = self.read_control(Request::GetM0State, 0, 0)?;
+        Ok(M0State::from_bytes(&buffer))
+    }
+
+    pub fn max2837_read(&self, register: u8) -> Result<u16, Error> {
+        if usize::from(register) >= MAX2837_REGISTER_COUNT {
+            return Err(Error::Argument);
+        }
+        let buffer: [u8; 2] = self.read_control(Request::Max2837Read, 0, register.into())?;
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    pub fn max2837_write(&mut self, register: u8, value: u16) -> Result<(), Error> {
+        if usize::from(register) >= MAX2837_REGISTER_COUNT || value > MAX2837_REGISTER_MAX {
+            return Err(Error::Argument);
+        }
+        self.write_control(Request::Max2837Write, value, register.into(), &[])
+    }
+
+    pub fn max2837_dump(&self) -> Result<Max2837Registers, Error> {
+        let mut registers: [u16; MAX2837_REGISTER_COUNT] = [0; MAX2837_REGISTER_COUNT];
+        for (register, value) in registers.iter_mut().enumerate() {
+            *value = self.max2837_read(register as u8)?;
+        }
+        Ok(Max2837Registers(registers))
+    }
+
+    pub fn si5351c_read(&self, register: u8) -> Result<u8, Error> {
+        let buffer: [u8; 1] = self.read_control(Request::Si5351CRead, 0, register.into())?;
+        Ok(buffer[0])
+    }
+
+    pub fn si5351c_write(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        self.write_control(Request::Si5351CWrite, value.into(), register.into(), &[])
+    }
+
+    pub fn si5351c_status(&self) -> Result<Si5351cStatus, Error> {
+        Ok(self.si5351c_read(0)?.into())
+    }
+
+    pub fn si5351c_dump(&self) -> Result<Si5351cRegisters, Error> {
+        let mut registers: [u8; SI5351C_REGISTER_COUNT] = [0; SI5351C_REGISTER_COUNT];
+        for (register, value) in registers.iter_mut().enumerate() {
+            *value = self.si5351c_read(register as u8)?;
+        }
+        Ok(Si5351cRegisters(registers))
+    }
+
+    pub fn rffc5071_read(&self, register: u8) -> Result<u16, Error> {
+        if usize::from(register) >= RFFC5071_REGISTER_COUNT {
+            return Err(Error::Argument);
+        }
+        let buffer: [u8; 2] = self.read_control(Request::Rffc5071Read, 0, register.into())?;
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    pub fn rffc5071_write(&mut self, register: u8, value: u16) -> Result<(), Error> {
+        if usize::from(register) >= RFFC5071_REGISTER_COUNT {
+            return Err(Error::Argument);
+        }
+        self.write_control(Request::Rffc5071Write, value, register.into(), &[])
+    }
+
+    pub fn rffc5071_dump(&self) -> Result<Rffc5071Registers, Error> {
+        let mut registers: [u16; RFFC5071_REGISTER_COUNT] = [0; RFFC5071_REGISTER_COUNT];
+        for (register, value) in registers.iter_mut().enumerate() {
+            *value = self.rffc5071_read(register as u8)?;
+        }
+        Ok(Rffc5071Registers(registers))
+    }
+
+    pub fn spiflash_erase(&mut self) -> Result<(), Error> {
+        self.transport
+            .write_control(Request::SpiflashErase, 0, 0, &[], SPIFLASH_ERASE_TIMEOUT)?;
         Ok(())
     }
 
-    fn set_transceiver_mode(&mut self, mode: TransceiverMode) -> Result<(), Error> {
-        self.write_control(Request::SetTransceiverMode, mode.into(), 0, &mut [])
+    pub fn spiflash_read(&self, offset: u32, len: usize) -> Result<Vec<u8>, Error> {
+        self.spiflash_read_with_progress(offset, len, |_, _| {})
     }
 
-    pub fn enter_rx_mode(&mut self) -> Result<(), Error> {
-        self.set_transceiver_mode(TransceiverMode::Receive)?;
-        self.device_handle.claim_interface(0)?;
-        self.mode = Mode::Rx;
+    // `progress` gets the bytes done so far and the total after every page.
+    pub fn spiflash_read_with_progress<F>(
+        &self,
+        offset: u32,
+        len: usize,
+        mut progress: F,
+    ) -> Result<Vec<u8>, Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        let offset: usize = offset as usize;
+        match offset.checked_add(len) {
+            Some(end) if end <= SPIFLASH_SIZE => {}
+            _ => return Err(Error::Argument),
+        }
+
+        let mut data: Vec<u8> = vec![0; len];
+        for (address, size) in pages(offset, len) {
+            let start: usize = address - offset;
+            let n: usize = self.transport.read_control(
+                Request::SpiflashRead,
+                (address >> 16) as u16,
+                (address & 0xFFFF) as u16,
+                &mut data[start..start + size],
+                self.timeout,
+            )?;
+            if n != size {
+                return Err(Error::ControlTransfer {
+                    direction: Direction::In,
+                    actual: n,
+                    expected: size,
+                });
+            }
+            progress(start + size, len);
+        }
+        Ok(data)
+    }
+
+    pub fn spiflash_write(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        self.spiflash_write_with_progress(offset, data, |_, _| {})
+    }
+
+    // Pages have to be erased first, the flash can only clear bits.
+    pub fn spiflash_write_with_progress<F>(
+        &mut self,
+        offset: u32,
+        data: &[u8],
+        mut progress: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        let offset: usize = offset as usize;
+        match offset.checked_add(data.len()) {
+            Some(end) if end <= SPIFLASH_SIZE => {}
+            _ => return Err(Error::Argument),
+        }
+
+        for (address, size) in pages(offset, data.len()) {
+            let start: usize = address - offset;
+            self.write_control(
+                Request::SpiflashWrite,
+                (address >> 16) as u16,
+                (address & 0xFFFF) as u16,
+                &data[start..start + size],
+            )?;
+            progress(start + size, data.len());
+        }
         Ok(())
     }
 
-    pub fn enter_tx_mode(&mut self) -> Result<(), Error> {
-        self.set_transceiver_mode(TransceiverMode::Transmit)?;
-        self.device_handle.claim_interface(0)?;
-        self.mode = Mode::Tx;
+    pub fn spiflash_status(&self) -> Result<SpiflashStatus, Error> {
+        self.check_api_version(Version::from_bcd(0x0103))?;
+        let buffer: [u8; 2] = self.read_control(Request::SpiflashStatus, 0, 0)?;
+        Ok(SpiflashStatus {
+            status1: buffer[0],
+            status2: buffer[1],
+        })
+    }
+
+    pub fn spiflash_clear_status(&mut self) -> Result<(), Error> {
+        self.check_api_version(Version::from_bcd(0x0103))?;
+        self.write_control(Request::SpiflashClearStatus, 0, 0, &[])
+    }
+
+    pub fn cpld_checksum(&self) -> Result<u32, Error> {
+        self.check_api_version(Version::from_bcd(0x0103))?;
+        let buffer: [u8; 4] = self.read_control(Request::CpldChecksum, 0, 0)?;
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    pub fn cpld_update(self, bitstream: &[u8]) -> Result<CpldUpdate, Error> {
+        self.cpld_update_with_progress(bitstream, |_, _| {})
+    }
+
+    // Streams an XSVF bitstream to the CPLD in 512-byte chunks, the last one
+    // zero-padded. The board has to be reset afterwards, see `CpldUpdate`.
+    pub fn cpld_update_with_progress<F>(
+        mut self,
+        bitstream: &[u8],
+        mut progress: F,
+    ) -> Result<CpldUpdate, Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        if bitstream.is_empty() {
+            return Err(Error::Argument);
+        }
+        let update: CpldUpdate = CpldUpdate {
+            serial_number: self.part_id_serial_read()?.serial,
+            previous_checksum: self.cpld_checksum()?,
+        };
+
+        self.set_transceiver_mode(TransceiverMode::CpldUpdate)?;
+        self.transport.claim_interface(0)?;
+        self.claimed = true;
+
+        for (i, chunk) in bitstream.chunks(CPLD_CHUNK_SIZE).enumerate() {
+            let mut buffer: [u8; CPLD_CHUNK_SIZE] = [0; CPLD_CHUNK_SIZE];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            let n: usize =
+                self.transport
+                    .write_bulk(TX_ENDPOINT_ADDRESS, &buffer, CPLD_CHUNK_TIMEOUT)?;
+            if n != buffer.len() {
+                return Err(Error::Usb(rusb::Error::Io));
+            }
+            progress(i * CPLD_CHUNK_SIZE + chunk.len(), bitstream.len());
+        }
+
+        self.transport.release_interface(0)?;
+        self.claimed = false;
+        Ok(update)
+    }
+
+    // Erases the flash, writes `image`, reads it back and restarts the board
+    // into the new firmware. On any failure the device comes back with the
+    // error; the board keeps running until it is reset, so the update can be
+    // retried.
+    pub fn update_firmware(mut self, image: &FirmwareImage) -> Result<(), (HackRF, Error)> {
+        match self.write_firmware(image).and_then(|()| self.send_reset()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    fn write_firmware(&mut self, image: &FirmwareImage) -> Result<(), Error> {
+        let kind: BoardKind = self.board_id()?.kind().unwrap_or(self.kind);
+        if !image.targets(kind) {
+            log::warn!(
+                "Firmware image does not look like it was built for {}",
+                kind
+            );
+        }
+
+        self.spiflash_erase()?;
+        self.spiflash_write(0, image.data())?;
+        if self.spiflash_read(0, image.data().len())? != image.data() {
+            return Err(Error::Verification);
+        }
         Ok(())
     }
 
-    pub fn rx(&mut self) -> Result<Vec<u8>, Error> {
-        let mut buffer: Vec<u8> = vec![0; MAX_TRANSMISSION_UNIT];
-        let n: usize =
-            self.device_handle
-                .read_bulk(RX_ENDPOINT_ADDRESS, &mut buffer, self.timeout)?;
-        buffer.truncate(n);
+    // Addresses of the Opera Cake boards found on the expansion header.
+    pub fn operacake_boards(&self) -> Result<Vec<u8>, Error> {
+        let buffer: [u8; OPERACAKE_MAX_BOARDS] =
+            self.read_control(Request::OperacakeGetBoards, 0, 0)?;
+        Ok(buffer
+            .into_iter()
+            .take_while(|address| *address != OPERACAKE_ADDRESS_INVALID)
+            .collect())
+    }
 
-        Ok(buffer)
+    pub fn operacake_set_ports(
+        &mut self,
+        address: u8,
+        port_a: OperacakePort,
+        port_b: OperacakePort,
+    ) -> Result<(), Error> {
+        if usize::from(address) >= OPERACAKE_MAX_BOARDS || port_a.is_a_side() == port_b.is_a_side()
+        {
+            return Err(Error::Argument);
+        }
+        let index: u16 = u16::from(u8::from(port_a)) | (u16::from(u8::from(port_b)) << 8);
+        self.write_control(Request::OperacakeSetPorts, address.into(), index, &[])
     }
 
-    pub fn tx(&mut self, mut buffer: Vec<u8>) -> Result<(), Error> {
-        buffer.truncate(MAX_TRANSMISSION_UNIT);
-        self.device_handle
-            .write_bulk(TX_ENDPOINT_ADDRESS, &mut buffer, self.timeout)?;
+    pub fn operacake_set_mode(&mut self, address: u8, mode: OperacakeMode) -> Result<(), Error> {
+        self.check_api_version(Version::from_bcd(0x0105))?;
+        if usize::from(address) >= OPERACAKE_MAX_BOARDS {
+            return Err(Error::Argument);
+        }
+        self.write_control(Request::OperacakeSetMode, address.into(), mode.into(), &[])?;
 
+        let bit: u8 = 1 << address;
+        if mode == OperacakeMode::Time {
+            self.operacake_time_boards |= bit;
+        } else if self.operacake_time_boards & bit != 0 {
+            self.operacake_time_boards &= !bit;
+            // the last board to leave time mode takes the schedule with it
+            if self.operacake_time_boards == 0 {
+                self.operacake_schedule = None;
+            }
+        }
         Ok(())
     }
 
-    pub fn stop_rx(&mut self) -> Result<(), Error> {
-        self.device_handle.release_interface(0)?;
-        self.set_transceiver_mode(TransceiverMode::Off)?;
-        self.mode = Mode::Off;
+    pub fn operacake_mode(&self, address: u8) -> Result<OperacakeMode, Error> {
+        self.check_api_version(Version::from_bcd(0x0105))?;
+        if usize::from(address) >= OPERACAKE_MAX_BOARDS {
+            return Err(Error::Argument);
+        }
+        let buffer: [u8; 1] = self.read_control(Request::OperacakeGetMode, address.into(), 0)?;
+        OperacakeMode::try_from(buffer[0])
+    }
+
+    // Each (start, stop) range in MHz selects the port A is switched to while
+    // the radio is tuned inside it; B gets the opposite port. The ranges are
+    // shared by every board put into `OperacakeMode::Frequency`.
+    pub fn operacake_set_ranges(
+        &mut self,
+        ranges: &[(u16, u16, OperacakePort)],
+    ) -> Result<(), Error> {
+        self.check_api_version(Version::from_bcd(0x0103))?;
+        if ranges.is_empty()
+            || ranges.len() > OPERACAKE_MAX_RANGES
+            || ranges.iter().any(|(start, stop, _)| start > stop)
+        {
+            return Err(Error::Argument);
+        }
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(5 * ranges.len());
+        for (start, stop, port) in ranges {
+            buffer.extend_from_slice(&start.to_be_bytes());
+            buffer.extend_from_slice(&stop.to_be_bytes());
+            buffer.push((*port).into());
+        }
+        self.write_control(Request::OperacakeSetRanges, 0, 0, &buffer)
+    }
+
+    // Loads the (port, dwell in samples) list that boards in
+    // `OperacakeMode::Time` cycle through while streaming. Port A follows the
+    // list and B gets the opposite port.
+    pub fn operacake_set_dwell_times(
+        &mut self,
+        dwells: &[(OperacakePort, u32)],
+    ) -> Result<(), Error> {
+        self.check_api_version(Version::from_bcd(0x0105))?;
+        let schedule: OperacakeSchedule = OperacakeSchedule::new(dwells)?;
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(5 * dwells.len());
+        for (port, dwell) in dwells {
+            buffer.extend_from_slice(&dwell.to_le_bytes());
+            buffer.push((*port).into());
+        }
+        self.write_control(Request::OperacakeSetDwellTimes, 0, 0, &buffer)?;
+        self.operacake_schedule = Some(schedule);
         Ok(())
     }
 
-    pub fn stop_tx(&mut self) -> Result<(), Error> {
-        self.device_handle.release_interface(0)?;
-        self.set_transceiver_mode(TransceiverMode::Off)?;
-        self.mode = Mode::Off;
+    pub fn operacake_schedule(&self) -> Option<&OperacakeSchedule> {
+        self.operacake_schedule.as_ref()
+    }
+
+    pub fn operacake_gpio_test(&self, address: u8) -> Result<OperacakeGpioTest, Error> {
+        self.check_api_version(Version::from_bcd(0x0103))?;
+        if usize::from(address) >= OPERACAKE_MAX_BOARDS {
+            return Err(Error::Argument);
+        }
+        let buffer: [u8; 2] = self.read_control(Request::OperacakeGpioTest, address.into(), 0)?;
+        Ok(OperacakeGpioTest(u16::from_le_bytes(buffer)))
+    }
+
+    pub fn reset(mut self) -> Result<(), Error> {
+        self.send_reset()
+    }
+
+    fn send_reset(&mut self) -> Result<(), Error> {
+        self.check_api_version(Version::from_bcd(0x0102))?;
+        self.write_control(Request::Reset, 0, 0, &[])?;
+        // the board comes back with everything off and under a new handle
+        self.active = false;
+        self.claimed = false;
+        self.amp_enabled = false;
+        self.antenna_enabled = false;
+        Ok(())
+    }
+
+    fn set_transceiver_mode(&mut self, mode: TransceiverMode) -> Result<(), Error> {
+        self.write_control(Request::SetTransceiverMode, mode.into(), 0, &[])
+    }
+
+    // The mode changes hand the device back with the error when the board
+    // refuses, so a failed attempt does not cost the handle.
+    pub fn enter_rx_mode(mut self) -> Result<Receiver, (HackRF, Error)> {
+        match self.start(TransceiverMode::Receive) {
+            Ok(()) => Ok(Receiver::new(self)),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    // Enters RX with hardware sync on, so nothing is streamed until the
+    // trigger input fires; see `RxStream::wait_for_trigger`.
+    // The receiver switches sync back off when it is stopped or dropped.
+    pub fn enter_armed_rx_mode(mut self) -> Result<Receiver, (HackRF, Error)> {
+        if let Err(e) = self.set_hw_sync_mode(HwSyncMode::On) {
+            return Err((self, e));
+        }
+        self.hw_sync_armed = true;
+        if let Err(e) = self.start(TransceiverMode::Receive) {
+            if self.set_hw_sync_mode(HwSyncMode::Off).is_ok() {
+                self.hw_sync_armed = false;
+            }
+            return Err((self, e));
+        }
+        Ok(Receiver::new(self))
+    }
+
+    pub fn enter_tx_mode(mut self) -> Result<Transmitter, (HackRF, Error)> {
+        match self.start(TransceiverMode::Transmit) {
+            Ok(()) => Ok(Transmitter::new(self)),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    // Sets up the sweep the firmware runs in sweep mode: each (start, stop)
+    // range in MHz is stepped through in `step_width` Hz steps, capturing
+    // `num_bytes` per step with the radio tuned `offset` Hz above the step.
+    pub fn init_sweep(
+        &mut self,
+        ranges: &[(u16, u16)],
+        num_bytes: u32,
+        step_width: u32,
+        offset: u32,
+        style: SweepStyle,
+    ) -> Result<(), Error> {
+        self.check_api_version(Version::from_bcd(0x0102))?;
+        if ranges.is_empty()
+            || ranges.len() > MAX_SWEEP_RANGES
+            || ranges.iter().any(|(start, stop)| start >= stop)
+            || num_bytes == 0
+            || !(num_bytes as usize).is_multiple_of(SWEEP_BLOCK_SIZE)
+            || step_width == 0
+        {
+            return Err(Error::Argument);
+        }
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(9 + 4 * ranges.len());
+        buffer.extend_from_slice(&step_width.to_le_bytes());
+        buffer.extend_from_slice(&offset.to_le_bytes());
+        buffer.push(style.into());
+        for (start, stop) in ranges {
+            buffer.extend_from_slice(&start.to_le_bytes());
+            buffer.extend_from_slice(&stop.to_le_bytes());
+        }
+
+        self.write_control(
+            Request::InitSweep,
+            (num_bytes & 0xFFFF) as u16,
+            (num_bytes >> 16) as u16,
+            &buffer,
+        )?;
+        self.sweep_offset = Some(offset);
+        Ok(())
+    }
+
+    pub fn enter_sweep_mode(mut self) -> Result<Sweeper, (HackRF, Error)> {
+        let offset: u32 = match self.sweep_offset {
+            Some(offset) => offset,
+            None => return Err((self, Error::Argument)),
+        };
+        match self.start(TransceiverMode::RxSweep) {
+            Ok(()) => Ok(Sweeper::new(self, offset)),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    // On failure the transceiver is switched back off, so the device can be
+    // handed back idle.
+    fn start(&mut self, mode: TransceiverMode) -> Result<(), Error> {
+        self.set_transceiver_mode(mode)?;
+        self.active = true;
+        if let Err(e) = self.transport.claim_interface(0) {
+            if self.set_transceiver_mode(TransceiverMode::Off).is_ok() {
+                self.active = false;
+            }
+            return Err(e);
+        }
+        self.claimed = true;
+        Ok(())
+    }
+
+    // Each step is undone only once, so a stop that failed half way can be
+    // called again.
+    fn stop(&mut self) -> Result<(), Error> {
+        if self.claimed {
+            self.transport.release_interface(0)?;
+            self.claimed = false;
+        }
+        if self.active {
+            self.set_transceiver_mode(TransceiverMode::Off)?;
+            self.active = false;
+        }
+        if self.hw_sync_armed {
+            self.set_hw_sync_mode(HwSyncMode::Off)?;
+            self.hw_sync_armed = false;
+        }
         Ok(())
     }
 }
 
+// Handles that only looked at the board (enumeration, info queries) are
+// dropped without sending anything, so another process's stream is left alone.
+impl Drop for HackRF {
+    fn drop(&mut self) {
+        if self.active {
+            if let Err(e) = self.set_transceiver_mode(TransceiverMode::Off) {
+                log::warn!("Failed to turn off transceiver on drop: {}", e);
+            }
+        }
+        if self.hw_sync_armed {
+            if let Err(e) = self.set_hw_sync_mode(HwSyncMode::Off) {
+                log::warn!("Failed to disable hardware sync on drop: {}", e);
+            }
+        }
+        if self.amp_enabled {
+            if let Err(e) = self.set_amp_enable(false) {
+                log::warn!("Failed to disable amplifier on drop: {}", e);
+            }
+        }
+        if self.antenna_enabled {
+            if let Err(e) = self.set_antenna_enable(0) {
+                log::warn!("Failed to disable antenna power on drop: {}", e);
+            }
+        }
+        if self.claimed {
+            if let Err(e) = self.transport.release_interface(0) {
+                log::warn!("Failed to release interface on drop: {}", e);
+            }
+        }
+    }
+}
+
 fn freq_params(hz: u64) -> [u8; 8] {
     let l_freq_mhz: u32 = u32::try_from(hz / MHZ).unwrap_or(u32::MAX).to_le();
     let l_freq_hz: u32 = u32::try_from(hz - u64::from(l_freq_mhz) * MHZ)
@@ -404,6 +1116,17 @@ pub enum Error {
         minimal: Version,
     },
     Argument,
+    NotFound,
+    Busy,
+    PermissionDenied,
+    Unsupported,
+    Io(std::io::ErrorKind),
+    // data read back from the device differs from what was written
+    Verification,
+    // a replayed session diverged from the recording at this line
+    Replay {
+        line: usize,
+    },
 }
 
 impl From<rusb::Error> for Error {
@@ -412,6 +1135,12 @@ impl From<rusb::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error.kind())
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(formatter, "{:?}", self)
@@ -0,0 +1,45 @@
+// Bookkeeping of the M0 core that moves samples between the radio and the
+// USB buffer. A shortfall is a stretch where the host did not keep up: RX
+// samples were dropped, or TX ran out and zeros were sent.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct M0State {
+    pub requested_mode: u16,
+    pub request_flag: u16,
+    pub active_mode: u32,
+    // bytes moved by the M0 and consumed or produced by the M4 (USB side)
+    pub m0_count: u32,
+    pub m4_count: u32,
+    pub num_shortfalls: u32,
+    pub longest_shortfall: u32,
+    pub shortfall_limit: u32,
+    pub threshold: u32,
+    pub next_mode: u32,
+    pub error: u32,
+}
+
+pub(crate) const M0_STATE_SIZE: usize = 40;
+
+impl M0State {
+    pub(crate) fn from_bytes(buffer: &[u8; M0_STATE_SIZE]) -> M0State {
+        let u16_at = |offset: usize| u16::from_le_bytes([buffer[offset], buffer[offset + 1]]);
+        let u32_at = |offset: usize| {
+            let mut bytes: [u8; 4] = [0; 4];
+            bytes.copy_from_slice(&buffer[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+
+        M0State {
+            requested_mode: u16_at(0),
+            request_flag: u16_at(2),
+            active_mode: u32_at(4),
+            m0_count: u32_at(8),
+            m4_count: u32_at(12),
+            num_shortfalls: u32_at(16),
+            longest_shortfall: u32_at(20),
+            shortfall_limit: u32_at(24),
+            threshold: u32_at(28),
+            next_mode: u32_at(32),
+            error: u32_at(36),
+        }
+    }
+}
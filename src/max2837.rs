@@ -0,0 +1,76 @@
+pub(crate) const MAX2837_REGISTER_COUNT: usize = 32;
+
+// largest value that fits the transceiver's 10-bit registers
+pub(crate) const MAX2837_REGISTER_MAX: u16 = 0x3FF;
+
+// LPF corner frequencies selected by the FT field, in Hz
+const LPF_BANDWIDTHS: [u32; 16] = [
+    1_750_000, 2_500_000, 3_500_000, 5_000_000, 5_500_000, 6_000_000, 7_000_000, 8_000_000,
+    9_000_000, 10_000_000, 12_000_000, 14_000_000, 15_000_000, 20_000_000, 24_000_000, 28_000_000,
+];
+
+// Snapshot of all MAX2837 registers with the fields that matter when
+// debugging gain and filter settings decoded. Field positions follow the
+// firmware's register definitions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Max2837Registers(pub [u16; MAX2837_REGISTER_COUNT]);
+
+impl Max2837Registers {
+    fn field(&self, register: usize, lsb: u32, width: u32) -> u16 {
+        (self.0[register] >> lsb) & ((1 << width) - 1)
+    }
+
+    // The chip is only powered up through SPI; RX and TX are switched by
+    // the board's GPIO pins, which the registers do not show.
+    pub fn chip_enabled(&self) -> bool {
+        self.field(16, 0, 1) == 1
+    }
+
+    pub fn lna_gain_spi_enabled(&self) -> bool {
+        self.field(0, 5, 1) == 1
+    }
+
+    // None for codes the firmware never writes
+    pub fn lna_gain(&self) -> Option<u16> {
+        match self.field(1, 2, 3) {
+            0 => Some(40),
+            1 => Some(32),
+            2 => Some(24),
+            3 => Some(16),
+            6 => Some(8),
+            7 => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn vga_gain(&self) -> u16 {
+        62 - 2 * self.field(5, 0, 5)
+    }
+
+    // the firmware stores the attenuation rotated left by one bit
+    pub fn txvga_gain(&self) -> u16 {
+        let raw: u16 = self.field(29, 0, 6);
+        let attenuation: u16 = (raw >> 1) | ((raw & 1) << 5);
+        47u16.saturating_sub(attenuation)
+    }
+
+    pub fn lpf_bandwidth(&self) -> u32 {
+        LPF_BANDWIDTHS[self.field(8, 0, 4) as usize]
+    }
+}
+
+impl From<[u16; MAX2837_REGISTER_COUNT]> for Max2837Registers {
+    fn from(registers: [u16; MAX2837_REGISTER_COUNT]) -> Self {
+        Max2837Registers(registers)
+    }
+}
+
+// same layout as `hackrf_debug --max2837 -r`
+impl std::fmt::Display for Max2837Registers {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (register, value) in self.0.iter().enumerate() {
+            writeln!(formatter, "[{:2}] -> 0x{:03x}", register, value)?;
+        }
+        Ok(())
+    }
+}
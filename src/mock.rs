@@ -0,0 +1,207 @@
+use crate::constants::*;
+use crate::request::Request;
+use crate::transport::Transport;
+use crate::Error;
+use rusb::Version;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockRecord {
+    ControlIn {
+        request: Request,
+        value: u16,
+        index: u16,
+        length: usize,
+    },
+    ControlOut {
+        request: Request,
+        value: u16,
+        index: u16,
+        data: Vec<u8>,
+    },
+    BulkIn {
+        endpoint: u8,
+        length: usize,
+    },
+    BulkOut {
+        endpoint: u8,
+        data: Vec<u8>,
+    },
+    ClaimInterface(u8),
+    ReleaseInterface(u8),
+}
+
+#[derive(Debug)]
+struct MockState {
+    device_version: Version,
+    product_id: u16,
+    records: Vec<MockRecord>,
+    control: VecDeque<(Request, Result<Vec<u8>, Error>)>,
+    bulk_in: VecDeque<Result<Vec<u8>, Error>>,
+}
+
+// A transport that records every request and answers from scripted
+// responses. Clones share their state, so a test keeps one clone to script
+// and inspect while the `HackRF` owns the other.
+//
+// Unscripted control reads stall like the firmware does on unknown requests,
+// unscripted bulk reads time out, and writes always succeed.
+#[derive(Debug, Clone)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        MockTransport::new()
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> MockTransport {
+        MockTransport {
+            state: Arc::new(Mutex::new(MockState {
+                device_version: Version::from_bcd(0x0106),
+                product_id: HACKRF_ONE_USB_PID,
+                records: Vec::new(),
+                control: VecDeque::new(),
+                bulk_in: VecDeque::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_device_version(&self, version: Version) {
+        self.state().device_version = version;
+    }
+
+    pub fn set_product_id(&self, product_id: u16) {
+        self.state().product_id = product_id;
+    }
+
+    // Queues the answer to the next `request`. Reads get the bytes, writes
+    // only look at whether it is an error.
+    pub fn respond(&self, request: Request, response: Result<Vec<u8>, Error>) {
+        self.state().control.push_back((request, response));
+    }
+
+    pub fn push_bulk(&self, response: Result<Vec<u8>, Error>) {
+        self.state().bulk_in.push_back(response);
+    }
+
+    pub fn records(&self) -> Vec<MockRecord> {
+        self.state().records.clone()
+    }
+
+    pub fn clear_records(&self) {
+        self.state().records.clear();
+    }
+
+    fn take_response(state: &mut MockState, request: Request) -> Option<Result<Vec<u8>, Error>> {
+        let position: usize = state.control.iter().position(|(r, _)| *r == request)?;
+        state.control.remove(position).map(|(_, response)| response)
+    }
+}
+
+impl Transport for MockTransport {
+    fn device_version(&self) -> Version {
+        self.state().device_version
+    }
+
+    fn product_id(&self) -> u16 {
+        self.state().product_id
+    }
+
+    fn read_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let mut state: MutexGuard<MockState> = self.state();
+        state.records.push(MockRecord::ControlIn {
+            request,
+            value,
+            index,
+            length: buffer.len(),
+        });
+
+        let data: Vec<u8> = Self::take_response(&mut state, request)
+            .unwrap_or(Err(Error::Usb(rusb::Error::Pipe)))?;
+        let n: usize = data.len().min(buffer.len());
+        buffer[..n].copy_from_slice(&data[..n]);
+        Ok(n)
+    }
+
+    fn write_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let mut state: MutexGuard<MockState> = self.state();
+        state.records.push(MockRecord::ControlOut {
+            request,
+            value,
+            index,
+            data: buffer.to_vec(),
+        });
+
+        match Self::take_response(&mut state, request) {
+            Some(Err(e)) => Err(e),
+            _ => Ok(buffer.len()),
+        }
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buffer: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let mut state: MutexGuard<MockState> = self.state();
+        state.records.push(MockRecord::BulkIn {
+            endpoint,
+            length: buffer.len(),
+        });
+
+        let data: Vec<u8> = state
+            .bulk_in
+            .pop_front()
+            .unwrap_or(Err(Error::Usb(rusb::Error::Timeout)))?;
+        let n: usize = data.len().min(buffer.len());
+        buffer[..n].copy_from_slice(&data[..n]);
+        Ok(n)
+    }
+
+    fn write_bulk(&self, endpoint: u8, buffer: &[u8], _timeout: Duration) -> Result<usize, Error> {
+        self.state().records.push(MockRecord::BulkOut {
+            endpoint,
+            data: buffer.to_vec(),
+        });
+        Ok(buffer.len())
+    }
+
+    fn claim_interface(&self, interface: u8) -> Result<(), Error> {
+        self.state()
+            .records
+            .push(MockRecord::ClaimInterface(interface));
+        Ok(())
+    }
+
+    fn release_interface(&self, interface: u8) -> Result<(), Error> {
+        self.state()
+            .records
+            .push(MockRecord::ReleaseInterface(interface));
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use crate::Error;
+use std::ops::Range;
+
+pub(crate) const OPERACAKE_MAX_BOARDS: usize = 8;
+pub(crate) const OPERACAKE_MAX_RANGES: usize = 8;
+pub(crate) const OPERACAKE_MAX_DWELL_TIMES: usize = 16;
+// marks an empty slot in the board list
+pub(crate) const OPERACAKE_ADDRESS_INVALID: u8 = 0xFF;
+// returned by the GPIO test when the board is not in GPIO mode
+const GPIO_TEST_DISABLED: u16 = 0xFFFF;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OperacakePort {
+    A1 = 0,
+    A2 = 1,
+    A3 = 2,
+    A4 = 3,
+    B1 = 4,
+    B2 = 5,
+    B3 = 6,
+    B4 = 7,
+}
+
+impl OperacakePort {
+    // The A and B inputs each switch onto one side of the board, so they can
+    // never be connected to ports on the same side.
+    pub fn is_a_side(&self) -> bool {
+        (*self as u8) < 4
+    }
+}
+
+impl From<OperacakePort> for u8 {
+    fn from(port: OperacakePort) -> Self {
+        port as u8
+    }
+}
+
+impl TryFrom<u8> for OperacakePort {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Error> {
+        match value {
+            0 => Ok(OperacakePort::A1),
+            1 => Ok(OperacakePort::A2),
+            2 => Ok(OperacakePort::A3),
+            3 => Ok(OperacakePort::A4),
+            4 => Ok(OperacakePort::B1),
+            5 => Ok(OperacakePort::B2),
+            6 => Ok(OperacakePort::B3),
+            7 => Ok(OperacakePort::B4),
+            _ => Err(Error::Argument),
+        }
+    }
+}
+
+impl std::fmt::Display for OperacakePort {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let port: u8 = *self as u8;
+        let side: char = if self.is_a_side() { 'A' } else { 'B' };
+        write!(formatter, "{}{}", side, port % 4 + 1)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OperacakeMode {
+    // ports only change on `operacake_set_ports`
+    Manual = 0,
+    // port A follows the tuned frequency, see `operacake_set_ranges`
+    Frequency = 1,
+    // ports cycle through a dwell-time schedule while streaming
+    Time = 2,
+}
+
+impl From<OperacakeMode> for u16 {
+    fn from(mode: OperacakeMode) -> Self {
+        mode as u16
+    }
+}
+
+impl TryFrom<u8> for OperacakeMode {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Error> {
+        match value {
+            0 => Ok(OperacakeMode::Manual),
+            1 => Ok(OperacakeMode::Frequency),
+            2 => Ok(OperacakeMode::Time),
+            _ => Err(Error::Argument),
+        }
+    }
+}
+
+// Result of the GPIO self-test for one switch control line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OperacakePin {
+    pub name: &'static str,
+    pub stuck_high: bool,
+    pub shorted: bool,
+    pub stuck_low: bool,
+}
+
+impl OperacakePin {
+    pub fn passed(&self) -> bool {
+        !(self.stuck_high || self.shorted || self.stuck_low)
+    }
+}
+
+// The raw GPIO test word: three failure bits per control line, u2ctrl1 in
+// the lowest bits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OperacakeGpioTest(pub u16);
+
+impl OperacakeGpioTest {
+    // the test only runs on boards jumpered for GPIO control
+    pub fn enabled(&self) -> bool {
+        self.0 != GPIO_TEST_DISABLED
+    }
+
+    pub fn passed(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn pins(&self) -> Option<[OperacakePin; 5]> {
+        if !self.enabled() {
+            return None;
+        }
+        let names: [&'static str; 5] = ["u2ctrl1", "u2ctrl0", "u3ctrl1", "u3ctrl0", "u1ctrl"];
+        Some(std::array::from_fn(|i| {
+            let bits: u16 = self.0 >> (3 * i);
+            OperacakePin {
+                name: names[i],
+                stuck_high: bits & 0x4 != 0,
+                shorted: bits & 0x2 != 0,
+                stuck_low: bits & 0x1 != 0,
+            }
+        }))
+    }
+}
+
+// The samples of a block captured while one port was switched in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperacakeSegment {
+    pub port: OperacakePort,
+    pub samples: Range<usize>,
+}
+
+// A time-mode schedule: each port is held for its dwell time in samples,
+// then the board moves on to the next entry and wraps around at the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperacakeSchedule {
+    dwells: Vec<(OperacakePort, u32)>,
+    period: u64,
+}
+
+impl OperacakeSchedule {
+    pub fn new(dwells: &[(OperacakePort, u32)]) -> Result<OperacakeSchedule, Error> {
+        if dwells.is_empty()
+            || dwells.len() > OPERACAKE_MAX_DWELL_TIMES
+            || dwells.iter().any(|(_, dwell)| *dwell == 0)
+        {
+            return Err(Error::Argument);
+        }
+        Ok(OperacakeSchedule {
+            dwells: dwells.to_vec(),
+            period: dwells.iter().map(|(_, dwell)| u64::from(*dwell)).sum(),
+        })
+    }
+
+    pub fn dwells(&self) -> &[(OperacakePort, u32)] {
+        &self.dwells
+    }
+
+    // samples in one pass through the schedule
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    pub fn port_at(&self, sample: u64) -> OperacakePort {
+        self.segments(sample, 1)[0].port
+    }
+
+    // Splits `len` samples starting at stream position `offset` into runs
+    // taken from the same port.
+    pub fn segments(&self, offset: u64, len: usize) -> Vec<OperacakeSegment> {
+        let mut position: u64 = offset % self.period;
+        let mut entry: usize = 0;
+        while position >= u64::from(self.dwells[entry].1) {
+            position -= u64::from(self.dwells[entry].1);
+            entry += 1;
+        }
+
+        let mut segments: Vec<OperacakeSegment> = Vec::new();
+        let mut start: usize = 0;
+        while start < len {
+            let (port, dwell) = self.dwells[entry];
+            let left: u64 = u64::from(dwell) - position;
+            let end: usize = (start as u64 + left).min(len as u64) as usize;
+            segments.push(OperacakeSegment {
+                port,
+                samples: start..end,
+            });
+            start = end;
+            position = 0;
+            entry = (entry + 1) % self.dwells.len();
+        }
+        segments
+    }
+}
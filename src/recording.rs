@@ -0,0 +1,555 @@
+use crate::request::Request;
+use crate::transport::Transport;
+use crate::Error;
+use rusb::Version;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+// Session files are plain text: a header line with the device's API version
+// and product id, then one line per transfer, e.g.
+//
+//   hackrf-session 1 0106 6089
+//   control_in 19 0 16 1 ok 01
+//   control_out 16 0 0 9303000000000000 ok 8
+//   bulk_in 129 512 err Timeout
+//
+// Payloads are hex, "-" when empty.
+const MAGIC: &str = "hackrf-session";
+const FORMAT_VERSION: u32 = 1;
+
+const USB_ERRORS: [rusb::Error; 14] = [
+    rusb::Error::Io,
+    rusb::Error::InvalidParam,
+    rusb::Error::Access,
+    rusb::Error::NoDevice,
+    rusb::Error::NotFound,
+    rusb::Error::Busy,
+    rusb::Error::Timeout,
+    rusb::Error::Overflow,
+    rusb::Error::Pipe,
+    rusb::Error::Interrupted,
+    rusb::Error::NoMem,
+    rusb::Error::NotSupported,
+    rusb::Error::BadDescriptor,
+    rusb::Error::Other,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    ControlIn {
+        request: Request,
+        value: u16,
+        index: u16,
+        length: usize,
+        result: Result<Vec<u8>, Error>,
+    },
+    ControlOut {
+        request: Request,
+        value: u16,
+        index: u16,
+        data: Vec<u8>,
+        result: Result<usize, Error>,
+    },
+    BulkIn {
+        endpoint: u8,
+        length: usize,
+        result: Result<Vec<u8>, Error>,
+    },
+    BulkOut {
+        endpoint: u8,
+        data: Vec<u8>,
+        result: Result<usize, Error>,
+    },
+    ClaimInterface {
+        interface: u8,
+        result: Result<(), Error>,
+    },
+    ReleaseInterface {
+        interface: u8,
+        result: Result<(), Error>,
+    },
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "-".into();
+    }
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text == "-" {
+        return Some(Vec::new());
+    }
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Only USB errors can come back from a device, anything else is kept as Other.
+fn encode_error(error: &Error) -> String {
+    match error {
+        Error::Usb(e) => format!("{:?}", e),
+        _ => format!("{:?}", rusb::Error::Other),
+    }
+}
+
+fn decode_error(text: &str) -> Option<Error> {
+    USB_ERRORS
+        .iter()
+        .find(|e| format!("{:?}", e) == text)
+        .map(|e| Error::Usb(*e))
+}
+
+fn encode_result<T>(result: &Result<T, Error>, ok: impl Fn(&T) -> String) -> String {
+    match result {
+        Ok(value) => format!("ok {}", ok(value)).trim_end().into(),
+        Err(e) => format!("err {}", encode_error(e)),
+    }
+}
+
+impl Event {
+    fn encode(&self) -> String {
+        match self {
+            Event::ControlIn {
+                request,
+                value,
+                index,
+                length,
+                result,
+            } => format!(
+                "control_in {} {} {} {} {}",
+                u8::from(*request),
+                value,
+                index,
+                length,
+                encode_result(result, |data| encode_hex(data))
+            ),
+            Event::ControlOut {
+                request,
+                value,
+                index,
+                data,
+                result,
+            } => format!(
+                "control_out {} {} {} {} {}",
+                u8::from(*request),
+                value,
+                index,
+                encode_hex(data),
+                encode_result(result, |n| n.to_string())
+            ),
+            Event::BulkIn {
+                endpoint,
+                length,
+                result,
+            } => format!(
+                "bulk_in {} {} {}",
+                endpoint,
+                length,
+                encode_result(result, |data| encode_hex(data))
+            ),
+            Event::BulkOut {
+                endpoint,
+                data,
+                result,
+            } => format!(
+                "bulk_out {} {} {}",
+                endpoint,
+                encode_hex(data),
+                encode_result(result, |n| n.to_string())
+            ),
+            Event::ClaimInterface { interface, result } => format!(
+                "claim {} {}",
+                interface,
+                encode_result(result, |_| String::new())
+            ),
+            Event::ReleaseInterface { interface, result } => format!(
+                "release {} {}",
+                interface,
+                encode_result(result, |_| String::new())
+            ),
+        }
+    }
+
+    fn decode(line: &str) -> Option<Event> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        fn result<T>(
+            fields: &[&str],
+            ok: impl Fn(Option<&&str>) -> Option<T>,
+        ) -> Option<Result<T, Error>> {
+            match *fields.first()? {
+                "ok" => ok(fields.get(1)).map(Ok),
+                "err" => decode_error(fields.get(1)?).map(Err),
+                _ => None,
+            }
+        }
+        // a reply longer than the read that asked for it is corrupt
+        fn fits(result: Result<Vec<u8>, Error>, length: usize) -> Option<Result<Vec<u8>, Error>> {
+            match result {
+                Ok(ref data) if data.len() > length => None,
+                _ => Some(result),
+            }
+        }
+        let data = |field: Option<&&str>| decode_hex(field?);
+        let count = |field: Option<&&str>| field?.parse::<usize>().ok();
+
+        match fields[..] {
+            ["control_in", request, value, index, length, ref rest @ ..] => {
+                let length: usize = length.parse().ok()?;
+                Some(Event::ControlIn {
+                    request: Request::try_from(request.parse::<u8>().ok()?).ok()?,
+                    value: value.parse().ok()?,
+                    index: index.parse().ok()?,
+                    length,
+                    result: fits(result(rest, data)?, length)?,
+                })
+            }
+            ["control_out", request, value, index, payload, ref rest @ ..] => {
+                Some(Event::ControlOut {
+                    request: Request::try_from(request.parse::<u8>().ok()?).ok()?,
+                    value: value.parse().ok()?,
+                    index: index.parse().ok()?,
+                    data: decode_hex(payload)?,
+                    result: result(rest, count)?,
+                })
+            }
+            ["bulk_in", endpoint, length, ref rest @ ..] => {
+                let length: usize = length.parse().ok()?;
+                Some(Event::BulkIn {
+                    endpoint: endpoint.parse().ok()?,
+                    length,
+                    result: fits(result(rest, data)?, length)?,
+                })
+            }
+            ["bulk_out", endpoint, payload, ref rest @ ..] => Some(Event::BulkOut {
+                endpoint: endpoint.parse().ok()?,
+                data: decode_hex(payload)?,
+                result: result(rest, count)?,
+            }),
+            ["claim", interface, ref rest @ ..] => Some(Event::ClaimInterface {
+                interface: interface.parse().ok()?,
+                result: result(rest, |_| Some(()))?,
+            }),
+            ["release", interface, ref rest @ ..] => Some(Event::ReleaseInterface {
+                interface: interface.parse().ok()?,
+                result: result(rest, |_| Some(()))?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn version_to_bcd(version: Version) -> u16 {
+    (u16::from(version.major()) << 8)
+        | (u16::from(version.minor()) << 4)
+        | u16::from(version.sub_minor())
+}
+
+// Passes everything through to `inner` and writes each transfer with its
+// outcome to a session file. Bulk streams fall back to blocking transfers
+// so that their data ends up in the file too.
+#[derive(Debug)]
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new<P: AsRef<Path>>(inner: T, path: P) -> Result<Self, Error> {
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+        writeln!(
+            writer,
+            "{} {} {:04x} {:04x}",
+            MAGIC,
+            FORMAT_VERSION,
+            version_to_bcd(inner.device_version()),
+            inner.product_id()
+        )?;
+
+        Ok(RecordingTransport {
+            inner,
+            writer: Mutex::new(writer),
+        })
+    }
+
+    fn record(&self, event: Event) -> Result<(), Error> {
+        let mut writer: MutexGuard<BufWriter<File>> =
+            self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(writer, "{}", event.encode())?;
+        Ok(writer.flush()?)
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn device_version(&self) -> Version {
+        self.inner.device_version()
+    }
+
+    fn product_id(&self) -> u16 {
+        self.inner.product_id()
+    }
+
+    fn read_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, Error> {
+        let result: Result<usize, Error> = self
+            .inner
+            .read_control(request, value, index, buffer, timeout);
+        self.record(Event::ControlIn {
+            request,
+            value,
+            index,
+            length: buffer.len(),
+            result: result.map(|n| buffer[..n].to_vec()),
+        })?;
+        result
+    }
+
+    fn write_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, Error> {
+        let result: Result<usize, Error> = self
+            .inner
+            .write_control(request, value, index, buffer, timeout);
+        self.record(Event::ControlOut {
+            request,
+            value,
+            index,
+            data: buffer.to_vec(),
+            result,
+        })?;
+        result
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buffer: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, Error> {
+        let result: Result<usize, Error> = self.inner.read_bulk(endpoint, buffer, timeout);
+        self.record(Event::BulkIn {
+            endpoint,
+            length: buffer.len(),
+            result: result.map(|n| buffer[..n].to_vec()),
+        })?;
+        result
+    }
+
+    fn write_bulk(&self, endpoint: u8, buffer: &[u8], timeout: Duration) -> Result<usize, Error> {
+        let result: Result<usize, Error> = self.inner.write_bulk(endpoint, buffer, timeout);
+        self.record(Event::BulkOut {
+            endpoint,
+            data: buffer.to_vec(),
+            result,
+        })?;
+        result
+    }
+
+    fn claim_interface(&self, interface: u8) -> Result<(), Error> {
+        let result: Result<(), Error> = self.inner.claim_interface(interface);
+        self.record(Event::ClaimInterface { interface, result })?;
+        result
+    }
+
+    fn release_interface(&self, interface: u8) -> Result<(), Error> {
+        let result: Result<(), Error> = self.inner.release_interface(interface);
+        self.record(Event::ReleaseInterface { interface, result })?;
+        result
+    }
+}
+
+// Answers from a session file. Every call has to match the next recorded
+// transfer exactly, otherwise it fails with `Error::Replay` naming the line.
+// Clones share the position, so a test can check that nothing was left over.
+#[derive(Debug, Clone)]
+pub struct ReplayTransport {
+    device_version: Version,
+    product_id: u16,
+    events: Arc<Vec<Event>>,
+    position: Arc<Mutex<usize>>,
+}
+
+impl ReplayTransport {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let header: String = lines.next().ok_or(Error::Replay { line: 1 })??;
+        let (device_version, product_id) = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [MAGIC, format, version, product_id] if format.parse::<u32>() == Ok(FORMAT_VERSION) => {
+                match (
+                    u16::from_str_radix(version, 16),
+                    u16::from_str_radix(product_id, 16),
+                ) {
+                    (Ok(version), Ok(product_id)) => (Version::from_bcd(version), product_id),
+                    _ => return Err(Error::Replay { line: 1 }),
+                }
+            }
+            _ => return Err(Error::Replay { line: 1 }),
+        };
+
+        let mut events: Vec<Event> = Vec::new();
+        for (i, line) in lines.enumerate() {
+            events.push(Event::decode(&line?).ok_or(Error::Replay { line: i + 2 })?);
+        }
+
+        Ok(ReplayTransport {
+            device_version,
+            product_id,
+            events: Arc::new(events),
+            position: Arc::new(Mutex::new(0)),
+        })
+    }
+
+    // Transfers recorded but not asked for yet.
+    pub fn remaining(&self) -> usize {
+        self.events.len() - *self.position()
+    }
+
+    fn position(&self) -> MutexGuard<'_, usize> {
+        self.position.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Hands the next event to `replay`; a `None` means the call differs
+    // from the recording and the position stays where it is.
+    fn next<R>(&self, replay: impl FnOnce(&Event) -> Option<R>) -> Result<R, Error> {
+        let mut position: MutexGuard<usize> = self.position();
+        let mismatch: Error = Error::Replay {
+            line: *position + 2,
+        };
+
+        let value: R = self
+            .events
+            .get(*position)
+            .and_then(replay)
+            .ok_or(mismatch)?;
+        *position += 1;
+        Ok(value)
+    }
+}
+
+fn copy_data(data: &Result<Vec<u8>, Error>, buffer: &mut [u8]) -> Result<usize, Error> {
+    let data: &Vec<u8> = data.as_ref().map_err(|e| *e)?;
+    buffer[..data.len()].copy_from_slice(data);
+    Ok(data.len())
+}
+
+impl Transport for ReplayTransport {
+    fn device_version(&self) -> Version {
+        self.device_version
+    }
+
+    fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    fn read_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let length: usize = buffer.len();
+        let data: Result<Vec<u8>, Error> = self.next(|event| match event {
+            Event::ControlIn {
+                request: r,
+                value: v,
+                index: i,
+                length: l,
+                result,
+            } if (*r, *v, *i, *l) == (request, value, index, length) => Some(result.clone()),
+            _ => None,
+        })?;
+        copy_data(&data, buffer)
+    }
+
+    fn write_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        self.next(|event| match event {
+            Event::ControlOut {
+                request: r,
+                value: v,
+                index: i,
+                data,
+                result,
+            } if (*r, *v, *i, data.as_slice()) == (request, value, index, buffer) => Some(*result),
+            _ => None,
+        })?
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buffer: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let length: usize = buffer.len();
+        let data: Result<Vec<u8>, Error> = self.next(|event| match event {
+            Event::BulkIn {
+                endpoint: e,
+                length: l,
+                result,
+            } if (*e, *l) == (endpoint, length) => Some(result.clone()),
+            _ => None,
+        })?;
+        copy_data(&data, buffer)
+    }
+
+    fn write_bulk(&self, endpoint: u8, buffer: &[u8], _timeout: Duration) -> Result<usize, Error> {
+        self.next(|event| match event {
+            Event::BulkOut {
+                endpoint: e,
+                data,
+                result,
+            } if (*e, data.as_slice()) == (endpoint, buffer) => Some(*result),
+            _ => None,
+        })?
+    }
+
+    fn claim_interface(&self, interface: u8) -> Result<(), Error> {
+        self.next(|event| match event {
+            Event::ClaimInterface {
+                interface: i,
+                result,
+            } if *i == interface => Some(*result),
+            _ => None,
+        })?
+    }
+
+    fn release_interface(&self, interface: u8) -> Result<(), Error> {
+        self.next(|event| match event {
+            Event::ReleaseInterface {
+                interface: i,
+                result,
+            } if *i == interface => Some(*result),
+            _ => None,
+        })?
+    }
+}
@@ -1,4 +1,7 @@
+use crate::Error;
+
 #[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Request {
     SetTransceiverMode = 1,
@@ -36,6 +39,13 @@ pub enum Request {
     OperacakeGpioTest = 35,
     CpldChecksum = 36,
     UiEnable = 37,
+    OperacakeSetMode = 38,
+    OperacakeGetMode = 39,
+    OperacakeSetDwellTimes = 40,
+    GetM0State = 41,
+    GetClkinStatus = 44,
+    BoardRevRead = 45,
+    SupportedPlatformRead = 46,
 }
 
 impl From<Request> for u8 {
@@ -43,3 +53,55 @@ impl From<Request> for u8 {
         r as u8
     }
 }
+
+impl TryFrom<u8> for Request {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Error> {
+        match value {
+            1 => Ok(Request::SetTransceiverMode),
+            2 => Ok(Request::Max2837Write),
+            3 => Ok(Request::Max2837Read),
+            4 => Ok(Request::Si5351CWrite),
+            5 => Ok(Request::Si5351CRead),
+            6 => Ok(Request::SampleRateSet),
+            7 => Ok(Request::BasebandFilterBandwidthSet),
+            8 => Ok(Request::Rffc5071Write),
+            9 => Ok(Request::Rffc5071Read),
+            10 => Ok(Request::SpiflashErase),
+            11 => Ok(Request::SpiflashWrite),
+            12 => Ok(Request::SpiflashRead),
+            14 => Ok(Request::BoardIdRead),
+            15 => Ok(Request::VersionStringRead),
+            16 => Ok(Request::SetFreq),
+            17 => Ok(Request::AmpEnable),
+            18 => Ok(Request::BoardPartidSerialnoRead),
+            19 => Ok(Request::SetLnaGain),
+            20 => Ok(Request::SetVgaGain),
+            21 => Ok(Request::SetTxvgaGain),
+            23 => Ok(Request::AntennaEnable),
+            24 => Ok(Request::SetFreqExplicit),
+            25 => Ok(Request::UsbWcidVendorReq),
+            26 => Ok(Request::InitSweep),
+            27 => Ok(Request::OperacakeGetBoards),
+            28 => Ok(Request::OperacakeSetPorts),
+            29 => Ok(Request::SetHwSyncMode),
+            30 => Ok(Request::Reset),
+            31 => Ok(Request::OperacakeSetRanges),
+            32 => Ok(Request::ClkoutEnable),
+            33 => Ok(Request::SpiflashStatus),
+            34 => Ok(Request::SpiflashClearStatus),
+            35 => Ok(Request::OperacakeGpioTest),
+            36 => Ok(Request::CpldChecksum),
+            37 => Ok(Request::UiEnable),
+            38 => Ok(Request::OperacakeSetMode),
+            39 => Ok(Request::OperacakeGetMode),
+            40 => Ok(Request::OperacakeSetDwellTimes),
+            41 => Ok(Request::GetM0State),
+            44 => Ok(Request::GetClkinStatus),
+            45 => Ok(Request::BoardRevRead),
+            46 => Ok(Request::SupportedPlatformRead),
+            _ => Err(Error::Argument),
+        }
+    }
+}
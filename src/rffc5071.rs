@@ -0,0 +1,86 @@
+// Registers 0..=30 are reachable. The readback register 31, the only place
+// the chip reports PLL lock, is refused by the firmware, so lock state
+// cannot be read over USB.
+pub(crate) const RFFC5071_REGISTER_COUNT: usize = 31;
+
+const REF_HZ: f64 = 40_000_000.0;
+
+const P1_FREQ1: usize = 0x0C;
+const P2_FREQ1: usize = 0x0F;
+const SDI_CTRL: usize = 0x15;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rffc5071Path {
+    One,
+    Two,
+}
+
+// One set of synthesizer settings: LO = REF * fbkdiv * (n + frac / 2^24) / lodiv
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rffc5071Synth {
+    pub n: u16,
+    pub frac: u32,
+    pub lodiv: u8,
+    pub fbkdiv: u8,
+    pub vcosel: u8,
+}
+
+impl Rffc5071Synth {
+    pub fn vco_frequency(&self) -> f64 {
+        REF_HZ * f64::from(self.fbkdiv) * (f64::from(self.n) + f64::from(self.frac) / 16_777_216.0)
+    }
+
+    pub fn lo_frequency(&self) -> f64 {
+        self.vco_frequency() / f64::from(self.lodiv)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rffc5071Registers(pub [u16; RFFC5071_REGISTER_COUNT]);
+
+impl Rffc5071Registers {
+    pub fn enabled(&self) -> bool {
+        self.0[SDI_CTRL] & 0x4000 != 0
+    }
+
+    // the firmware runs both RX and TX on path 2
+    pub fn active_path(&self) -> Rffc5071Path {
+        if self.0[SDI_CTRL] & 0x2000 != 0 {
+            Rffc5071Path::Two
+        } else {
+            Rffc5071Path::One
+        }
+    }
+
+    pub fn synth(&self, path: Rffc5071Path) -> Rffc5071Synth {
+        let base: usize = match path {
+            Rffc5071Path::One => P1_FREQ1,
+            Rffc5071Path::Two => P2_FREQ1,
+        };
+        let (freq1, nmsb, nlsb) = (self.0[base], self.0[base + 1], self.0[base + 2]);
+
+        Rffc5071Synth {
+            n: freq1 >> 7,
+            frac: (u32::from(nmsb) << 8) | u32::from(nlsb >> 8),
+            lodiv: 1 << ((freq1 >> 4) & 0x07),
+            fbkdiv: match (freq1 >> 2) & 0x03 {
+                2 => 4,
+                _ => 2,
+            },
+            vcosel: (freq1 & 0x03) as u8,
+        }
+    }
+
+    pub fn lo_frequency(&self) -> f64 {
+        self.synth(self.active_path()).lo_frequency()
+    }
+}
+
+impl std::fmt::Display for Rffc5071Registers {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (register, value) in self.0.iter().enumerate() {
+            writeln!(formatter, "[{:2}] -> 0x{:04x}", register, value)?;
+        }
+        Ok(())
+    }
+}
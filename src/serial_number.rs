@@ -0,0 +1,78 @@
+use crate::Error;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SerialNumber(pub [u32; 4]);
+
+impl SerialNumber {
+    // same rule as `hackrf_transfer -d`: the given digits must match the end
+    // of the serial number, so the leading zeros can be left out
+    pub fn matches(&self, suffix: &str) -> bool {
+        let suffix: &str = suffix.strip_prefix("0x").unwrap_or(suffix);
+        !suffix.is_empty() && self.to_string().ends_with(&suffix.to_lowercase())
+    }
+}
+
+impl std::fmt::Display for SerialNumber {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for word in self.0 {
+            write!(formatter, "{:08x}", word)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SerialNumber {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: &str = s.strip_prefix("0x").unwrap_or(s);
+        if s.is_empty() || s.len() > 32 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::Argument);
+        }
+
+        let padded: String = format!("{:0>32}", s);
+        let mut serial: [u32; 4] = [0; 4];
+        for (i, word) in serial.iter_mut().enumerate() {
+            *word =
+                u32::from_str_radix(&padded[8 * i..8 * i + 8], 16).map_err(|_| Error::Argument)?;
+        }
+        Ok(SerialNumber(serial))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PartIdSerial {
+    pub part_id: [u32; 2],
+    pub serial: SerialNumber,
+}
+
+impl PartIdSerial {
+    pub(crate) fn from_bytes(buffer: &[u8; 24]) -> PartIdSerial {
+        let word = |i: usize| u32::from_le_bytes(buffer[4 * i..4 * i + 4].try_into().unwrap());
+
+        PartIdSerial {
+            part_id: [word(0), word(1)],
+            serial: SerialNumber([word(2), word(3), word(4), word(5)]),
+        }
+    }
+}
+
+// The text form is the serial number alone, as `hackrf_info` and `-d` use
+// it. The part id is not part of it and parses back as zeros.
+impl std::fmt::Display for PartIdSerial {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serial.fmt(formatter)
+    }
+}
+
+impl FromStr for PartIdSerial {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(PartIdSerial {
+            part_id: [0; 2],
+            serial: s.parse()?,
+        })
+    }
+}
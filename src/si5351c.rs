@@ -0,0 +1,218 @@
+pub(crate) const SI5351C_REGISTER_COUNT: usize = 256;
+
+const XTAL_HZ: f64 = 25_000_000.0;
+// the reference HackRF boards expect on CLKIN
+const CLKIN_HZ: f64 = 10_000_000.0;
+
+const STATUS: usize = 0;
+const OUTPUT_ENABLE: usize = 3;
+const PLL_INPUT_SOURCE: usize = 15;
+const CLK_CONTROL: usize = 16;
+const MSNA: usize = 26;
+const MSNB: usize = 34;
+const MS0: usize = 42;
+const MS6: usize = 90;
+const R6_R7_DIV: usize = 92;
+
+// the output wired to the CLKOUT connector
+const CLKOUT: u8 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Si5351cStatus {
+    pub sys_init: bool,
+    pub pll_a_locked: bool,
+    pub pll_b_locked: bool,
+    pub clkin_lost: bool,
+    pub xtal_lost: bool,
+}
+
+impl From<u8> for Si5351cStatus {
+    fn from(value: u8) -> Self {
+        Si5351cStatus {
+            sys_init: value & 0x80 != 0,
+            pll_b_locked: value & 0x40 == 0,
+            pll_a_locked: value & 0x20 == 0,
+            clkin_lost: value & 0x10 != 0,
+            xtal_lost: value & 0x08 != 0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pll {
+    A,
+    B,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClockSource {
+    Xtal,
+    Clkin,
+    // MS0 for CLK0..CLK3, MS4 for CLK4..CLK7
+    GroupMultisynth,
+    Multisynth,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClockControl {
+    pub powered_down: bool,
+    pub integer_mode: bool,
+    pub pll: Pll,
+    pub inverted: bool,
+    pub source: ClockSource,
+    pub drive_ma: u8,
+}
+
+impl From<u8> for ClockControl {
+    fn from(value: u8) -> Self {
+        ClockControl {
+            powered_down: value & 0x80 != 0,
+            integer_mode: value & 0x40 != 0,
+            pll: if value & 0x20 != 0 { Pll::B } else { Pll::A },
+            inverted: value & 0x10 != 0,
+            source: match (value >> 2) & 0x03 {
+                0 => ClockSource::Xtal,
+                1 => ClockSource::Clkin,
+                2 => ClockSource::GroupMultisynth,
+                _ => ClockSource::Multisynth,
+            },
+            drive_ma: 2 * ((value & 0x03) + 1),
+        }
+    }
+}
+
+// Divider a + b/c packed the way the chip stores it:
+// p1 = 128a + floor(128b/c) - 512, p2 = 128b - c * floor(128b/c), p3 = c.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Multisynth {
+    pub p1: u32,
+    pub p2: u32,
+    pub p3: u32,
+    pub divide_by_4: bool,
+    // output divider as a power of two
+    pub r_div: u8,
+}
+
+impl Multisynth {
+    fn from_registers(registers: &[u8]) -> Multisynth {
+        let r: [u32; 8] = std::array::from_fn(|i| u32::from(registers[i]));
+        Multisynth {
+            p1: ((r[2] & 0x03) << 16) | (r[3] << 8) | r[4],
+            p2: ((r[5] & 0x0F) << 16) | (r[6] << 8) | r[7],
+            p3: ((r[5] & 0xF0) << 12) | (r[0] << 8) | r[1],
+            divide_by_4: r[2] & 0x0C == 0x0C,
+            r_div: ((r[2] >> 4) & 0x07) as u8,
+        }
+    }
+
+    pub fn ratio(&self) -> Option<f64> {
+        if self.divide_by_4 {
+            return Some(4.0);
+        }
+        if self.p3 == 0 {
+            return None;
+        }
+        Some((f64::from(self.p1) + 512.0 + f64::from(self.p2) / f64::from(self.p3)) / 128.0)
+    }
+}
+
+// Snapshot of the Si5351C register file with decoders for the clock tree.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Si5351cRegisters(pub [u8; SI5351C_REGISTER_COUNT]);
+
+impl Si5351cRegisters {
+    pub fn status(&self) -> Si5351cStatus {
+        self.0[STATUS].into()
+    }
+
+    pub fn output_enabled(&self, clock: u8) -> bool {
+        self.0[OUTPUT_ENABLE] & (1 << (clock & 0x07)) == 0
+    }
+
+    pub fn clock_control(&self, clock: u8) -> ClockControl {
+        self.0[CLK_CONTROL + usize::from(clock & 0x07)].into()
+    }
+
+    pub fn pll_source(&self, pll: Pll) -> ClockSource {
+        let bit: u8 = match pll {
+            Pll::A => 0x04,
+            Pll::B => 0x08,
+        };
+        if self.0[PLL_INPUT_SOURCE] & bit != 0 {
+            ClockSource::Clkin
+        } else {
+            ClockSource::Xtal
+        }
+    }
+
+    pub fn pll_multisynth(&self, pll: Pll) -> Multisynth {
+        let base: usize = match pll {
+            Pll::A => MSNA,
+            Pll::B => MSNB,
+        };
+        Multisynth::from_registers(&self.0[base..base + 8])
+    }
+
+    // MS6 and MS7 only have an 8-bit integer divider
+    pub fn multisynth(&self, index: u8) -> Multisynth {
+        match index & 0x07 {
+            index @ 0..=5 => {
+                let base: usize = MS0 + 8 * usize::from(index);
+                Multisynth::from_registers(&self.0[base..base + 8])
+            }
+            index => {
+                let shift: u8 = if index == 6 { 0 } else { 4 };
+                Multisynth {
+                    p1: u32::from(self.0[MS6 + usize::from(index - 6)]),
+                    p2: 0,
+                    p3: 1,
+                    divide_by_4: false,
+                    r_div: (self.0[R6_R7_DIV] >> shift) & 0x07,
+                }
+            }
+        }
+    }
+
+    pub fn pll_frequency(&self, pll: Pll) -> Option<f64> {
+        let reference: f64 = match self.pll_source(pll) {
+            ClockSource::Clkin => CLKIN_HZ / f64::from(1u8 << (self.0[PLL_INPUT_SOURCE] >> 6)),
+            _ => XTAL_HZ,
+        };
+        Some(reference * self.pll_multisynth(pll).ratio()?)
+    }
+
+    // What a CLK output is programmed to produce, assuming the nominal
+    // crystal and CLKIN frequencies. None when powered down or unprogrammed.
+    pub fn clock_frequency(&self, clock: u8) -> Option<f64> {
+        let clock: u8 = clock & 0x07;
+        let control: ClockControl = self.clock_control(clock);
+        if control.powered_down {
+            return None;
+        }
+
+        let index: u8 = match control.source {
+            ClockSource::Xtal => return Some(XTAL_HZ),
+            ClockSource::Clkin => return Some(CLKIN_HZ),
+            ClockSource::GroupMultisynth => clock & 0x04,
+            ClockSource::Multisynth => clock,
+        };
+        let multisynth: Multisynth = self.multisynth(index);
+        let pll: Pll = self.clock_control(index).pll;
+        let ratio: f64 = match index {
+            6 | 7 => f64::from(multisynth.p1),
+            _ => multisynth.ratio()?,
+        };
+        if ratio == 0.0 {
+            return None;
+        }
+
+        Some(self.pll_frequency(pll)? / ratio / f64::from(1u8 << self.multisynth(clock).r_div))
+    }
+
+    pub fn clkout_frequency(&self) -> Option<f64> {
+        if !self.output_enabled(CLKOUT) {
+            return None;
+        }
+        self.clock_frequency(CLKOUT)
+    }
+}
@@ -0,0 +1,490 @@
+use crate::constants::*;
+use crate::request::Request;
+use crate::sweep::{SweepStyle, SWEEP_BLOCK_SIZE};
+use crate::transceiver_mode::TransceiverMode;
+use crate::transport::Transport;
+use crate::tuning::{FreqPlan, RfPathFilter};
+use crate::{Complex, Error, SerialNumber};
+use rusb::Version;
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+// Amplitudes are in full-scale units (1.0 is the ADC limit) at 0 dB of
+// receive gain. Everything the LNA, VGA and amplifier add scales them up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Signal {
+    Tone { freq_hz: u64, amplitude: f64 },
+    Noise { amplitude: f64 },
+}
+
+// The sweep loaded with InitSweep, stepped through the way the firmware does.
+#[derive(Debug)]
+struct SweepPlan {
+    ranges: Vec<(u16, u16)>,
+    dwell_blocks: u32,
+    step_width: u64,
+    offset: u64,
+    style: SweepStyle,
+    range: usize,
+    sweep_freq: u64,
+    odd: bool,
+    blocks_queued: u32,
+}
+
+impl SweepPlan {
+    fn decode(num_bytes: u32, buffer: &[u8]) -> Result<SweepPlan, Error> {
+        let ranges: Vec<(u16, u16)> = buffer
+            .get(9..)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .map(|r| {
+                (
+                    u16::from_le_bytes([r[0], r[1]]),
+                    u16::from_le_bytes([r[2], r[3]]),
+                )
+            })
+            .collect();
+        let style: SweepStyle = match buffer.get(8) {
+            Some(0) => SweepStyle::Linear,
+            Some(1) => SweepStyle::Interleaved,
+            _ => return Err(Error::Usb(rusb::Error::Pipe)),
+        };
+        if ranges.is_empty() {
+            return Err(Error::Usb(rusb::Error::Pipe));
+        }
+
+        Ok(SweepPlan {
+            sweep_freq: u64::from(ranges[0].0) * MHZ,
+            ranges,
+            dwell_blocks: num_bytes / SWEEP_BLOCK_SIZE as u32,
+            step_width: u64::from(u32_at(buffer, 0)?),
+            offset: u64::from(u32_at(buffer, 4)?),
+            style,
+            range: 0,
+            odd: true,
+            blocks_queued: 0,
+        })
+    }
+
+    fn restart(&mut self) {
+        self.range = 0;
+        self.sweep_freq = u64::from(self.ranges[0].0) * MHZ;
+        self.odd = true;
+        self.blocks_queued = 0;
+    }
+
+    fn advance(&mut self) {
+        self.blocks_queued += 1;
+        if self.blocks_queued < self.dwell_blocks {
+            return;
+        }
+        self.blocks_queued = 0;
+
+        let stop: u64 = u64::from(self.ranges[self.range].1) * MHZ;
+        let last_step: bool = self.sweep_freq + self.step_width >= stop;
+        match self.style {
+            SweepStyle::Interleaved if !self.odd && last_step => self.next_range(),
+            SweepStyle::Interleaved if self.odd => self.sweep_freq += self.step_width / 4,
+            SweepStyle::Interleaved => self.sweep_freq += 3 * self.step_width / 4,
+            SweepStyle::Linear if last_step => self.next_range(),
+            SweepStyle::Linear => self.sweep_freq += self.step_width,
+        }
+        if self.style == SweepStyle::Interleaved {
+            self.odd = !self.odd;
+        }
+    }
+
+    fn next_range(&mut self) {
+        self.range = (self.range + 1) % self.ranges.len();
+        self.sweep_freq = u64::from(self.ranges[self.range].0) * MHZ;
+    }
+}
+
+#[derive(Debug)]
+struct SimulatorState {
+    serial_number: SerialNumber,
+    mode: TransceiverMode,
+    freq_hz: u64,
+    sample_rate: f64,
+    baseband_filter_bandwidth: u32,
+    lna_gain: u16,
+    vga_gain: u16,
+    txvga_gain: u16,
+    amp_enabled: bool,
+    antenna_enabled: bool,
+    // with hardware sync on, RX stays silent until `trigger`
+    hw_sync: bool,
+    triggered: bool,
+    // the next RX transfer fails on the bus and its samples are gone
+    lose_transfer: bool,
+    signals: Vec<Signal>,
+    // samples produced since the simulator was created, the time base for
+    // every tone so phases stay continuous across reads and retunes
+    sample_count: u64,
+    rng: u64,
+    tx_samples: Vec<Complex<i8>>,
+    sweep: Option<SweepPlan>,
+}
+
+impl SimulatorState {
+    fn gain(&self) -> f64 {
+        let amp: u16 = if self.amp_enabled { 14 } else { 0 };
+        10f64.powf(f64::from(self.lna_gain + self.vga_gain + amp) / 20.0)
+    }
+
+    // xorshift64*, plenty for noise and keeps runs reproducible
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits: u64 = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        (bits as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    fn gaussian(&mut self) -> Complex<f64> {
+        let radius: f64 = (-2.0 * self.uniform().ln()).sqrt();
+        let angle: f64 = 2.0 * PI * self.uniform();
+        Complex::from_polar(radius / 2f64.sqrt(), angle)
+    }
+
+    fn fill_rx(&mut self, buffer: &mut [u8]) {
+        let gain: f64 = self.gain();
+        let nyquist: f64 = self.sample_rate / 2.0;
+        let signals: Vec<Signal> = self.signals.clone();
+
+        for pair in buffer.chunks_exact_mut(2) {
+            let t: f64 = self.sample_count as f64 / self.sample_rate;
+            let mut sample: Complex<f64> = Complex::new(0.0, 0.0);
+
+            for signal in &signals {
+                match *signal {
+                    Signal::Tone { freq_hz, amplitude } => {
+                        let offset: f64 = freq_hz as f64 - self.freq_hz as f64;
+                        if offset.abs() <= nyquist {
+                            sample += Complex::from_polar(amplitude, 2.0 * PI * offset * t);
+                        }
+                    }
+                    Signal::Noise { amplitude } => sample += self.gaussian() * amplitude,
+                }
+            }
+
+            sample *= gain * 127.0;
+            pair[0] = sample.re.round().clamp(-128.0, 127.0) as i8 as u8;
+            pair[1] = sample.im.round().clamp(-128.0, 127.0) as i8 as u8;
+            self.sample_count += 1;
+        }
+    }
+}
+
+// A HackRF One in software. It answers the vendor requests the driver
+// sends, keeps the resulting settings, produces synthetic IQ while receiving
+// and captures whatever is transmitted. Clones share the same device.
+#[derive(Debug, Clone)]
+pub struct SimulatedHackRF {
+    state: Arc<Mutex<SimulatorState>>,
+}
+
+impl Default for SimulatedHackRF {
+    fn default() -> Self {
+        SimulatedHackRF::new()
+    }
+}
+
+impl SimulatedHackRF {
+    pub fn new() -> SimulatedHackRF {
+        SimulatedHackRF {
+            state: Arc::new(Mutex::new(SimulatorState {
+                serial_number: SerialNumber([0, 0, 0x5349_4D55, 0x4C41_5445]),
+                mode: TransceiverMode::Off,
+                freq_hz: 0,
+                sample_rate: 10_000_000.0,
+                baseband_filter_bandwidth: 0,
+                lna_gain: 0,
+                vga_gain: 0,
+                txvga_gain: 0,
+                amp_enabled: false,
+                antenna_enabled: false,
+                hw_sync: false,
+                triggered: false,
+                lose_transfer: false,
+                signals: Vec::new(),
+                sample_count: 0,
+                rng: 0x9E37_79B9_7F4A_7C15,
+                tx_samples: Vec::new(),
+                sweep: None,
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SimulatorState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_serial_number(&self, serial_number: SerialNumber) {
+        self.state().serial_number = serial_number;
+    }
+
+    pub fn add_signal(&self, signal: Signal) {
+        self.state().signals.push(signal);
+    }
+
+    pub fn clear_signals(&self) {
+        self.state().signals.clear();
+    }
+
+    pub fn mode(&self) -> TransceiverMode {
+        self.state().mode
+    }
+
+    pub fn freq(&self) -> u64 {
+        self.state().freq_hz
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.state().sample_rate
+    }
+
+    pub fn baseband_filter_bandwidth(&self) -> u32 {
+        self.state().baseband_filter_bandwidth
+    }
+
+    pub fn lna_gain(&self) -> u16 {
+        self.state().lna_gain
+    }
+
+    pub fn vga_gain(&self) -> u16 {
+        self.state().vga_gain
+    }
+
+    pub fn txvga_gain(&self) -> u16 {
+        self.state().txvga_gain
+    }
+
+    pub fn amp_enabled(&self) -> bool {
+        self.state().amp_enabled
+    }
+
+    pub fn antenna_enabled(&self) -> bool {
+        self.state().antenna_enabled
+    }
+
+    pub fn hw_sync(&self) -> bool {
+        self.state().hw_sync
+    }
+
+    // a rising edge on the trigger input
+    pub fn trigger(&self) {
+        self.state().triggered = true;
+    }
+
+    pub fn lose_transfer(&self) {
+        self.state().lose_transfer = true;
+    }
+
+    // Everything transmitted since the last call.
+    pub fn take_tx_samples(&self) -> Vec<Complex<i8>> {
+        std::mem::take(&mut self.state().tx_samples)
+    }
+}
+
+fn u32_at(buffer: &[u8], offset: usize) -> Result<u32, Error> {
+    buffer
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(Error::Usb(rusb::Error::Pipe))
+}
+
+fn u64_at(buffer: &[u8], offset: usize) -> Result<u64, Error> {
+    Ok(u64::from(u32_at(buffer, offset)?) | (u64::from(u32_at(buffer, offset + 4)?) << 32))
+}
+
+impl Transport for SimulatedHackRF {
+    fn device_version(&self) -> Version {
+        Version::from_bcd(0x0106)
+    }
+
+    fn product_id(&self) -> u16 {
+        HACKRF_ONE_USB_PID
+    }
+
+    fn read_control(
+        &self,
+        request: Request,
+        _value: u16,
+        index: u16,
+        buffer: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let mut state: MutexGuard<SimulatorState> = self.state();
+
+        let data: Vec<u8> = match request {
+            Request::BoardIdRead => vec![2],
+            Request::BoardRevRead => vec![0xFF],
+            Request::SupportedPlatformRead => 0b0010u32.to_be_bytes().to_vec(),
+            // simulated boards always run from a shared reference
+            Request::GetClkinStatus => vec![1],
+            // the host always keeps up with a simulated board
+            Request::GetM0State => vec![0; 40],
+            Request::VersionStringRead => b"simulated".to_vec(),
+            Request::BoardPartidSerialnoRead => {
+                let mut data: Vec<u8> = vec![0; 8];
+                for word in state.serial_number.0 {
+                    data.extend_from_slice(&word.to_le_bytes());
+                }
+                data
+            }
+            // the firmware answers gain requests with 1 for a value it took
+            Request::SetLnaGain if index <= 40 => {
+                state.lna_gain = index;
+                vec![1]
+            }
+            Request::SetVgaGain if index <= 62 => {
+                state.vga_gain = index;
+                vec![1]
+            }
+            Request::SetTxvgaGain if index <= 47 => {
+                state.txvga_gain = index;
+                vec![1]
+            }
+            Request::SetLnaGain | Request::SetVgaGain | Request::SetTxvgaGain => vec![0],
+            _ => return Err(Error::Usb(rusb::Error::Pipe)),
+        };
+
+        let n: usize = data.len().min(buffer.len());
+        buffer[..n].copy_from_slice(&data[..n]);
+        Ok(n)
+    }
+
+    fn write_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &[u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let mut state: MutexGuard<SimulatorState> = self.state();
+
+        match request {
+            Request::SetTransceiverMode => {
+                state.mode =
+                    TransceiverMode::try_from(value).map_err(|_| Error::Usb(rusb::Error::Pipe))?;
+                state.triggered = false;
+                if state.mode == TransceiverMode::RxSweep {
+                    let sweep: &mut SweepPlan =
+                        state.sweep.as_mut().ok_or(Error::Usb(rusb::Error::Pipe))?;
+                    sweep.restart();
+                }
+            }
+            Request::InitSweep => {
+                let num_bytes: u32 = u32::from(value) | (u32::from(index) << 16);
+                state.sweep = Some(SweepPlan::decode(num_bytes, buffer)?);
+            }
+            Request::SetFreq => {
+                state.freq_hz = u64::from(u32_at(buffer, 0)?) * MHZ + u64::from(u32_at(buffer, 4)?);
+            }
+            Request::SetFreqExplicit => {
+                let plan: FreqPlan = FreqPlan {
+                    if_hz: u64_at(buffer, 0)?,
+                    lo_hz: u64_at(buffer, 8)?,
+                    path: buffer
+                        .get(16)
+                        .and_then(|path| RfPathFilter::try_from(*path).ok())
+                        .ok_or(Error::Usb(rusb::Error::Pipe))?,
+                };
+                plan.validate().map_err(|_| Error::Usb(rusb::Error::Pipe))?;
+                state.freq_hz = plan.rf_hz();
+            }
+            Request::SampleRateSet => {
+                let (hz, divider) = (u32_at(buffer, 0)?, u32_at(buffer, 4)?);
+                if divider == 0 {
+                    return Err(Error::Usb(rusb::Error::Pipe));
+                }
+                state.sample_rate = f64::from(hz) / f64::from(divider);
+            }
+            Request::BasebandFilterBandwidthSet => {
+                state.baseband_filter_bandwidth = u32::from(value) | (u32::from(index) << 16);
+            }
+            Request::AmpEnable => state.amp_enabled = value != 0,
+            Request::AntennaEnable => state.antenna_enabled = value != 0,
+            Request::SetHwSyncMode => state.hw_sync = value != 0,
+            Request::ClkoutEnable => {}
+            Request::Reset => {
+                state.mode = TransceiverMode::Off;
+                state.amp_enabled = false;
+                state.antenna_enabled = false;
+                state.hw_sync = false;
+            }
+            _ => return Err(Error::Usb(rusb::Error::Pipe)),
+        }
+        Ok(buffer.len())
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buffer: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, Error> {
+        let mut state: MutexGuard<SimulatorState> = self.state();
+        if endpoint != RX_ENDPOINT_ADDRESS {
+            return Err(Error::Usb(rusb::Error::Timeout));
+        }
+
+        match state.mode {
+            TransceiverMode::Receive if state.hw_sync && !state.triggered => {
+                return Err(Error::Usb(rusb::Error::Timeout))
+            }
+            TransceiverMode::Receive if state.lose_transfer => {
+                state.lose_transfer = false;
+                state.sample_count += (buffer.len() / 2) as u64;
+                return Err(Error::Usb(rusb::Error::Io));
+            }
+            TransceiverMode::Receive => state.fill_rx(buffer),
+            TransceiverMode::RxSweep => {
+                for block in buffer.chunks_mut(SWEEP_BLOCK_SIZE) {
+                    let (sweep_freq, offset) = match &state.sweep {
+                        Some(sweep) => (sweep.sweep_freq, sweep.offset),
+                        None => break,
+                    };
+                    state.freq_hz = sweep_freq + offset;
+                    state.fill_rx(block);
+
+                    if block.len() >= 10 {
+                        block[0] = 0x7F;
+                        block[1] = 0x7F;
+                        block[2..10].copy_from_slice(&sweep_freq.to_le_bytes());
+                    }
+                    if let Some(sweep) = state.sweep.as_mut() {
+                        sweep.advance();
+                    }
+                }
+            }
+            _ => return Err(Error::Usb(rusb::Error::Timeout)),
+        }
+        Ok(buffer.len() & !1)
+    }
+
+    fn write_bulk(&self, endpoint: u8, buffer: &[u8], _timeout: Duration) -> Result<usize, Error> {
+        let mut state: MutexGuard<SimulatorState> = self.state();
+        if endpoint != TX_ENDPOINT_ADDRESS || state.mode != TransceiverMode::Transmit {
+            return Err(Error::Usb(rusb::Error::Timeout));
+        }
+
+        state.tx_samples.extend(
+            buffer
+                .chunks_exact(2)
+                .map(|pair| Complex::new(pair[0] as i8, pair[1] as i8)),
+        );
+        Ok(buffer.len())
+    }
+
+    fn claim_interface(&self, _interface: u8) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn release_interface(&self, _interface: u8) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+pub(crate) const SPIFLASH_SIZE: usize = 0x10_0000;
+pub(crate) const SPIFLASH_PAGE_SIZE: usize = 256;
+// the firmware only acknowledges an erase once the whole chip is blank
+pub(crate) const SPIFLASH_ERASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Status registers 1 and 2 of the W25Q80BV flash.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpiflashStatus {
+    pub status1: u8,
+    pub status2: u8,
+}
+
+impl SpiflashStatus {
+    pub fn busy(&self) -> bool {
+        self.status1 & 0x01 != 0
+    }
+
+    pub fn write_enabled(&self) -> bool {
+        self.status1 & 0x02 != 0
+    }
+
+    // BP0..BP2; anything but zero write-protects part of the chip
+    pub fn block_protect(&self) -> u8 {
+        (self.status1 >> 2) & 0x07
+    }
+}
+
+// Splits `offset..offset + len` into pieces that never cross a page.
+pub(crate) fn pages(offset: usize, len: usize) -> impl Iterator<Item = (usize, usize)> {
+    let end: usize = offset + len;
+    let mut position: usize = offset;
+    std::iter::from_fn(move || {
+        if position >= end {
+            return None;
+        }
+        let next: usize = ((position / SPIFLASH_PAGE_SIZE + 1) * SPIFLASH_PAGE_SIZE).min(end);
+        let page: (usize, usize) = (position, next - position);
+        position = next;
+        Some(page)
+    })
+}
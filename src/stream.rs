@@ -0,0 +1,343 @@
+use crate::constants::*;
+use crate::iq::IqBlocks;
+use crate::operacake::OperacakeSchedule;
+use crate::transfer::TransferStatus;
+use crate::transport::BulkStream;
+use crate::{Error, HackRF, M0State};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StreamConfig {
+    pub transfer_count: usize,
+    pub transfer_size: usize,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        StreamConfig {
+            transfer_count: TRANSFER_COUNT,
+            transfer_size: TRANSFER_SIZE,
+        }
+    }
+}
+
+impl StreamConfig {
+    fn validate(&self) -> Result<(), Error> {
+        if self.transfer_count == 0
+            || self.transfer_size == 0
+            || !self.transfer_size.is_multiple_of(USB_MAX_PACKET_SIZE)
+        {
+            Err(Error::Argument)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    pub transfers: u64,
+    pub bytes: u64,
+    pub short_transfers: u64,
+    pub lost_transfers: u64,
+    // RX: times every transfer had completed before the oldest was handed
+    // back, leaving the board nowhere to put samples
+    pub overruns: u64,
+    // TX: transfers the producer left short while more data followed. The
+    // board running dry between transfers is not seen here, see
+    // `M0State::num_shortfalls`
+    pub underruns: u64,
+}
+
+#[derive(Debug)]
+pub struct RxTransfer<'a> {
+    pub sequence: u64,
+    // position of the first byte in the stream; a lost transfer still moves
+    // the position on by a full transfer so gaps show up downstream
+    pub offset: u64,
+    pub status: TransferStatus,
+    // the pool ran dry before this transfer was queued, so samples may be
+    // missing in front of it even though `offset` carries on
+    pub overrun: bool,
+    pub data: &'a [u8],
+}
+
+pub struct RxStream<'a> {
+    hackrf: &'a HackRF,
+    pool: Box<dyn BulkStream + 'a>,
+    head: usize,
+    timeout: Duration,
+    // the head transfer was handed out and goes back to libusb on the next call
+    pending: bool,
+    sequence: u64,
+    offset: u64,
+    stats: StreamStats,
+    schedule: Option<OperacakeSchedule>,
+    sample_rate: Option<f64>,
+    // slots that were resubmitted into a drained pool
+    overrun: Vec<bool>,
+}
+
+impl<'a> RxStream<'a> {
+    pub(crate) fn new(hackrf: &'a HackRF, config: StreamConfig) -> Result<Self, Error> {
+        config.validate()?;
+
+        let mut pool: Box<dyn BulkStream + 'a> =
+            hackrf.transport.bulk_stream(RX_ENDPOINT_ADDRESS, config)?;
+        for index in 0..pool.transfer_count() {
+            pool.submit(index, config.transfer_size)?;
+        }
+
+        Ok(RxStream {
+            hackrf,
+            overrun: vec![false; pool.transfer_count()],
+            pool,
+            head: 0,
+            timeout: hackrf.timeout,
+            pending: false,
+            sequence: 0,
+            offset: 0,
+            stats: StreamStats::default(),
+            // ports only follow the dwell times on boards in time mode
+            schedule: match hackrf.operacake_time_boards {
+                0 => None,
+                _ => hackrf.operacake_schedule.clone(),
+            },
+            sample_rate: hackrf.sample_rate,
+        })
+    }
+
+    pub fn next_transfer(&mut self) -> Result<RxTransfer<'_>, Error> {
+        if self.pending {
+            if self.pool.transfer_count() > 1 && self.pool.pending() == 0 {
+                self.overrun[self.head] = true;
+                self.stats.overruns += 1;
+            }
+            let size: usize = self.pool.buffer(self.head).len();
+            self.pool.submit(self.head, size)?;
+            self.head = (self.head + 1) % self.pool.transfer_count();
+            self.pending = false;
+        }
+
+        let head: usize = self.head;
+        self.pool.wait(head, self.timeout)?;
+        let (status, actual) = self.pool.status(head)?;
+        self.pending = true;
+
+        let transfer: RxTransfer = RxTransfer {
+            sequence: self.sequence,
+            offset: self.offset,
+            status,
+            overrun: std::mem::take(&mut self.overrun[head]),
+            data: &self.pool.buffer(head)[..actual],
+        };
+
+        self.sequence += 1;
+        self.stats.transfers += 1;
+        self.stats.bytes += actual as u64;
+        match status {
+            TransferStatus::Complete => self.offset += actual as u64,
+            TransferStatus::Short => {
+                self.offset += actual as u64;
+                self.stats.short_transfers += 1;
+            }
+            TransferStatus::Lost => {
+                self.offset += self.pool.buffer(head).len() as u64;
+                self.stats.lost_transfers += 1;
+            }
+        }
+
+        Ok(transfer)
+    }
+
+    // For streams on an armed receiver: blocks until the first transfer after
+    // the trigger completes and leaves it for `next_transfer`. The returned
+    // time is when the trigger fired, estimated as the arrival time less the
+    // time it took to fill the transfer when the sample rate is known.
+    pub fn wait_for_trigger(&mut self, timeout: Duration) -> Result<SystemTime, Error> {
+        if self.sequence != 0 {
+            return Err(Error::Argument);
+        }
+        self.pool.wait(self.head, timeout)?;
+        let arrival: SystemTime = SystemTime::now();
+        let (_, actual) = self.pool.status(self.head)?;
+
+        Ok(match self.sample_rate {
+            Some(rate) => arrival - Duration::from_secs_f64(actual as f64 / 2.0 / rate),
+            None => arrival,
+        })
+    }
+
+    // Hands every transfer to `callback` until it returns false.
+    pub fn for_each_transfer<F>(&mut self, mut callback: F) -> Result<StreamStats, Error>
+    where
+        F: FnMut(RxTransfer) -> bool,
+    {
+        loop {
+            if !callback(self.next_transfer()?) {
+                return Ok(self.stats);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+
+    // The firmware's own count of samples it had to drop, which also catches
+    // overruns the host cannot see.
+    pub fn m0_state(&self) -> Result<M0State, Error> {
+        self.hackrf.m0_state()
+    }
+
+    // The Opera Cake dwell times in effect when the stream was opened.
+    pub fn operacake_schedule(&self) -> Option<&OperacakeSchedule> {
+        self.schedule.as_ref()
+    }
+
+    pub fn iq_blocks(self) -> IqBlocks<'a> {
+        IqBlocks::new(self)
+    }
+}
+
+pub struct TxStream<'a> {
+    pool: Box<dyn BulkStream + 'a>,
+    head: usize,
+    timeout: Duration,
+    // the previous buffer was only partly filled; whether that was an underrun
+    // or the end of the stream is known once the producer is asked again
+    short_fill: bool,
+    stats: StreamStats,
+}
+
+impl<'a> TxStream<'a> {
+    pub(crate) fn new(hackrf: &'a HackRF, config: StreamConfig) -> Result<Self, Error> {
+        config.validate()?;
+
+        let pool: Box<dyn BulkStream + 'a> =
+            hackrf.transport.bulk_stream(TX_ENDPOINT_ADDRESS, config)?;
+
+        Ok(TxStream {
+            pool,
+            head: 0,
+            timeout: hackrf.timeout,
+            short_fill: false,
+            stats: StreamStats::default(),
+        })
+    }
+
+    fn complete(&mut self, index: usize) -> Result<(), Error> {
+        self.pool.wait(index, self.timeout)?;
+        let (status, actual) = self.pool.status(index)?;
+
+        self.stats.transfers += 1;
+        self.stats.bytes += actual as u64;
+        match status {
+            TransferStatus::Complete => {}
+            TransferStatus::Short => self.stats.short_transfers += 1,
+            TransferStatus::Lost => self.stats.lost_transfers += 1,
+        }
+        Ok(())
+    }
+
+    fn next_free(&mut self) -> Result<usize, Error> {
+        let head: usize = self.head;
+        if self.pool.in_flight(head) {
+            self.complete(head)?;
+        }
+        Ok(head)
+    }
+
+    fn submit_head(&mut self) -> Result<(), Error> {
+        let size: usize = self.pool.buffer(self.head).len();
+        self.pool.submit(self.head, size)?;
+        self.head = (self.head + 1) % self.pool.transfer_count();
+        Ok(())
+    }
+
+    // Fills transfers from `producer` until it returns `None`, then flushes.
+    // The producer returns how many bytes it wrote; the rest of the buffer is
+    // zero-filled and counted as an underrun unless the stream ends right after.
+    pub fn transmit<F>(&mut self, mut producer: F) -> Result<StreamStats, Error>
+    where
+        F: FnMut(&mut [u8]) -> Option<usize>,
+    {
+        loop {
+            let head: usize = self.next_free()?;
+            let buffer: &mut [u8] = self.pool.buffer_mut(head);
+
+            let n: usize = match producer(buffer) {
+                Some(n) => n.min(buffer.len()),
+                None => break,
+            };
+            buffer[n..].fill(0);
+
+            if self.short_fill {
+                self.stats.underruns += 1;
+            }
+            self.short_fill = n < buffer.len();
+            self.submit_head()?;
+        }
+
+        self.short_fill = false;
+        self.flush()?;
+        Ok(self.stats)
+    }
+
+    // Sends byte chunks from `receiver` until its sender hangs up. An empty
+    // channel does not block: the transfer goes out zero-filled instead.
+    pub fn transmit_from(&mut self, receiver: &Receiver<Vec<u8>>) -> Result<StreamStats, Error> {
+        let mut chunk: Vec<u8> = Vec::new();
+        let mut position: usize = 0;
+        let mut disconnected: bool = false;
+
+        self.transmit(|buffer: &mut [u8]| {
+            let mut n: usize = 0;
+            while n < buffer.len() && !disconnected {
+                if position == chunk.len() {
+                    match receiver.try_recv() {
+                        Ok(next) => {
+                            chunk = next;
+                            position = 0;
+                        }
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => disconnected = true,
+                    }
+                    continue;
+                }
+
+                let count: usize = (chunk.len() - position).min(buffer.len() - n);
+                buffer[n..n + count].copy_from_slice(&chunk[position..position + count]);
+                n += count;
+                position += count;
+            }
+
+            if n == 0 && disconnected {
+                None
+            } else {
+                Some(n)
+            }
+        })
+    }
+
+    // Waits for every queued transfer, then pushes one transfer of zeros
+    // through so the samples before it leave the device's own buffer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        for i in 0..self.pool.transfer_count() {
+            let index: usize = (self.head + i) % self.pool.transfer_count();
+            if self.pool.in_flight(index) {
+                self.complete(index)?;
+            }
+        }
+
+        let head: usize = self.next_free()?;
+        self.pool.buffer_mut(head).fill(0);
+        self.submit_head()?;
+        self.complete(head)
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.stats
+    }
+}
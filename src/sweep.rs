@@ -0,0 +1,99 @@
+use crate::iq::IqBlock;
+use crate::stream::RxStream;
+use crate::transfer::TransferStatus;
+use crate::Error;
+use num_complex::Complex;
+use std::collections::VecDeque;
+
+pub(crate) const SWEEP_BLOCK_SIZE: usize = 16_384;
+pub(crate) const MAX_SWEEP_RANGES: usize = 10;
+// the 0x7F 0x7F marker and the frequency, padded to keep samples aligned
+const SWEEP_HEADER_SIZE: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SweepStyle {
+    // step_width apart
+    Linear = 0,
+    // alternately a quarter and three quarters of step_width apart, so the
+    // two halves of each step can be stitched around the DC spike
+    Interleaved = 1,
+}
+
+impl From<SweepStyle> for u8 {
+    fn from(style: SweepStyle) -> Self {
+        style as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepBlock {
+    // frequency the radio was tuned to while the block was captured
+    pub center_hz: u64,
+    pub samples: Vec<Complex<i8>>,
+}
+
+impl SweepBlock {
+    // The firmware stamps each block with the sweep frequency, before the
+    // tuning offset is added. Blocks without the marker were captured while
+    // retuning and are dropped.
+    pub(crate) fn from_bytes(data: &[u8], offset: u32) -> Option<SweepBlock> {
+        if data.len() < SWEEP_HEADER_SIZE || data[0] != 0x7F || data[1] != 0x7F {
+            return None;
+        }
+        let mut frequency: [u8; 8] = [0; 8];
+        frequency.copy_from_slice(&data[2..10]);
+
+        Some(SweepBlock {
+            center_hz: u64::from_le_bytes(frequency) + u64::from(offset),
+            samples: IqBlock::from_bytes(0, &data[SWEEP_HEADER_SIZE..]).samples,
+        })
+    }
+}
+
+pub struct SweepBlocks<'a> {
+    stream: RxStream<'a>,
+    offset: u32,
+    pending: VecDeque<SweepBlock>,
+    failed: bool,
+}
+
+impl<'a> SweepBlocks<'a> {
+    pub(crate) fn new(stream: RxStream<'a>, offset: u32) -> Self {
+        SweepBlocks {
+            stream,
+            offset,
+            pending: VecDeque::new(),
+            failed: false,
+        }
+    }
+}
+
+impl Iterator for SweepBlocks<'_> {
+    type Item = Result<SweepBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            if self.failed {
+                return None;
+            }
+            match self.stream.next_transfer() {
+                Ok(transfer) if transfer.status == TransferStatus::Lost => continue,
+                Ok(transfer) => {
+                    let offset: u32 = self.offset;
+                    self.pending.extend(
+                        transfer
+                            .data
+                            .chunks(SWEEP_BLOCK_SIZE)
+                            .filter_map(|block| SweepBlock::from_bytes(block, offset)),
+                    );
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
@@ -0,0 +1,324 @@
+use crate::iq::IqBlock;
+use crate::stream::{RxStream, StreamConfig};
+use crate::{Complex, Error, HackRF, Receiver};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+// blocks buffered per device between its USB thread and the group
+const CHANNEL_CAPACITY: usize = 16;
+// how often an armed device checks for a stop while waiting for the trigger
+const TRIGGER_POLL: Duration = Duration::from_millis(100);
+
+// Settings applied to every device of a group.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SyncConfig {
+    pub freq_hz: u64,
+    pub sample_rate: u32,
+    pub lna_gain: u16,
+    pub vga_gain: u16,
+    pub amp_enable: bool,
+}
+
+// Boards sharing one reference clock and one trigger line. The first device
+// is the clock master: its CLKOUT is wired to the CLKIN of the others.
+#[derive(Debug)]
+pub struct SyncGroup {
+    devices: Vec<HackRF>,
+}
+
+impl SyncGroup {
+    pub fn open(serial_numbers: &[&str]) -> Result<SyncGroup, Error> {
+        let devices: Vec<HackRF> = serial_numbers
+            .iter()
+            .map(|serial_number| HackRF::open_by_serial(serial_number))
+            .collect::<Result<Vec<HackRF>, Error>>()?;
+        Self::from_devices(devices)
+    }
+
+    pub fn from_devices(mut devices: Vec<HackRF>) -> Result<SyncGroup, Error> {
+        if devices.is_empty() {
+            return Err(Error::Argument);
+        }
+        devices[0].set_clkout_enable(true)?;
+
+        for (index, device) in devices.iter().enumerate().skip(1) {
+            if !device.capabilities().clkin {
+                return Err(Error::Unsupported);
+            }
+            match device.clkin_status() {
+                Ok(true) | Err(Error::Version { .. }) => {}
+                Ok(false) => log::warn!("Device {} sees no clock on CLKIN", index),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(SyncGroup { devices })
+    }
+
+    pub fn devices(&self) -> &[HackRF] {
+        &self.devices
+    }
+
+    pub fn master(&self) -> &HackRF {
+        &self.devices[0]
+    }
+
+    pub fn configure(&mut self, config: &SyncConfig) -> Result<(), Error> {
+        for device in &mut self.devices {
+            device.set_sample_rate(config.sample_rate, 1)?;
+            device.set_freq(config.freq_hz)?;
+            device.set_lna_gain(config.lna_gain)?;
+            device.set_vga_gain(config.vga_gain)?;
+            device.set_amp_enable(config.amp_enable)?;
+        }
+        Ok(())
+    }
+
+    // Arms every device with hardware sync and streams each from its own
+    // thread. Nothing arrives until the shared trigger fires. If a device
+    // fails to arm, the ones already armed are stopped again and the group
+    // comes back with the error.
+    pub fn start(self, config: StreamConfig) -> Result<SyncStream, (SyncGroup, Error)> {
+        let mut receivers: Vec<Receiver> = Vec::with_capacity(self.devices.len());
+        let mut devices = self.devices.into_iter();
+        while let Some(device) = devices.next() {
+            match device.enter_armed_rx_mode() {
+                Ok(receiver) => receivers.push(receiver),
+                Err((device, e)) => {
+                    let (mut group, _) = stop_all(receivers);
+                    group.devices.push(device);
+                    group.devices.extend(devices);
+                    return Err((group, e));
+                }
+            }
+        }
+        Ok(SyncStream::spawn(receivers, config))
+    }
+}
+
+// Samples from every device of a group covering the same instants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupBlock {
+    // index of the first sample since the trigger
+    pub offset: u64,
+    // one run of samples per device, in group order
+    pub samples: Vec<Vec<Complex<i8>>>,
+    // a device lost samples in this block; lost transfers are zero-filled
+    // so the devices stay aligned, an overrun on the board is only flagged
+    pub dropped: bool,
+}
+
+// The samples one device has delivered beyond what the group handed out.
+#[derive(Debug, Default)]
+struct Lane {
+    samples: VecDeque<Complex<i8>>,
+    // stream position just after the last buffered sample
+    end: u64,
+    // zero-filled ranges still in the buffer
+    gaps: Vec<(u64, u64)>,
+    // positions where the board reported an overrun just before
+    overruns: Vec<u64>,
+}
+
+impl Lane {
+    fn push(&mut self, block: IqBlock) {
+        if block.offset > self.end {
+            let missing: usize = (block.offset - self.end) as usize;
+            self.samples
+                .extend(std::iter::repeat_n(Complex::new(0, 0), missing));
+            self.gaps.push((self.end, block.offset));
+        }
+        if block.overrun {
+            self.overruns.push(block.offset);
+        }
+        self.end = block.offset + block.samples.len() as u64;
+        self.samples.extend(block.samples);
+    }
+}
+
+pub struct SyncStream {
+    channels: Vec<mpsc::Receiver<Result<IqBlock, Error>>>,
+    exit: Arc<AtomicBool>,
+    threads: Vec<thread::JoinHandle<Receiver>>,
+    lanes: Vec<Lane>,
+    block_size: usize,
+    offset: u64,
+    dropped: bool,
+    failed: bool,
+    // how long `next` waits for a device before giving up
+    timeout: Duration,
+}
+
+impl SyncStream {
+    fn spawn(receivers: Vec<Receiver>, config: StreamConfig) -> SyncStream {
+        let timeout: Duration = receivers[0].device().timeout;
+        let exit: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let mut channels: Vec<mpsc::Receiver<Result<IqBlock, Error>>> = Vec::new();
+        let mut threads: Vec<thread::JoinHandle<Receiver>> = Vec::new();
+
+        for mut receiver in receivers {
+            let (sender, channel) = mpsc::sync_channel(CHANNEL_CAPACITY);
+            let exit_flag: Arc<AtomicBool> = exit.clone();
+            threads.push(thread::spawn(move || {
+                if let Err(e) = stream_device(&mut receiver, config, &sender, &exit_flag) {
+                    let _ = sender.send(Err(e));
+                }
+                receiver
+            }));
+            channels.push(channel);
+        }
+
+        SyncStream {
+            lanes: channels.iter().map(|_| Lane::default()).collect(),
+            channels,
+            exit,
+            threads,
+            block_size: config.transfer_size / 2,
+            offset: 0,
+            dropped: false,
+            failed: false,
+            timeout,
+        }
+    }
+
+    // Starts out as the master's USB timeout. Keep it above the time the
+    // trigger may take to fire, or retry `next` on a timeout.
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = duration;
+    }
+
+    // whether any device has lost samples since the stream started
+    pub fn dropped(&self) -> bool {
+        self.dropped
+    }
+
+    // Stops every device, switches hardware sync back off and returns the
+    // group; if any device failed to stop, with the first error next to it.
+    // A panic in one of the stream threads is passed on to the caller here.
+    pub fn stop(mut self) -> Result<SyncGroup, (SyncGroup, Error)> {
+        let receivers: Vec<Receiver> = self
+            .join()
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect();
+        match stop_all(receivers) {
+            (group, None) => Ok(group),
+            (group, Some(e)) => Err((group, e)),
+        }
+    }
+
+    fn join(&mut self) -> Vec<thread::Result<Receiver>> {
+        self.exit.store(true, Ordering::Relaxed);
+        // a thread blocked on a full channel wakes up once its receiver is gone
+        self.channels.clear();
+        self.threads.drain(..).map(|thread| thread.join()).collect()
+    }
+}
+
+impl Iterator for SyncStream {
+    type Item = Result<GroupBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        for (lane, channel) in self.lanes.iter_mut().zip(&self.channels) {
+            while lane.samples.len() < self.block_size {
+                match channel.recv_timeout(self.timeout) {
+                    Ok(Ok(block)) => lane.push(block),
+                    Ok(Err(e)) => {
+                        self.failed = true;
+                        return Some(Err(e));
+                    }
+                    // what has arrived stays buffered for the next call
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        return Some(Err(Error::Usb(rusb::Error::Timeout)))
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        self.failed = true;
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let start: u64 = self.offset;
+        let end: u64 = start + self.block_size as u64;
+        let mut dropped: bool = false;
+        let mut samples: Vec<Vec<Complex<i8>>> = Vec::with_capacity(self.lanes.len());
+        for lane in &mut self.lanes {
+            dropped |= lane
+                .gaps
+                .iter()
+                .any(|(from, to)| *from < end && *to > start);
+            lane.gaps.retain(|(_, to)| *to > end);
+            dropped |= lane.overruns.iter().any(|at| *at < end);
+            lane.overruns.retain(|at| *at >= end);
+            samples.push(lane.samples.drain(..self.block_size).collect());
+        }
+
+        self.offset = end;
+        self.dropped |= dropped;
+        Some(Ok(GroupBlock {
+            offset: start,
+            samples,
+            dropped,
+        }))
+    }
+}
+
+impl Drop for SyncStream {
+    fn drop(&mut self) {
+        for result in self.join() {
+            if result.is_err() {
+                log::warn!("Sync stream thread panicked");
+            }
+        }
+    }
+}
+
+// Stops each receiver in turn. A device that refuses is kept all the same,
+// still marked active so that dropping it switches it off.
+fn stop_all(receivers: Vec<Receiver>) -> (SyncGroup, Option<Error>) {
+    let mut devices: Vec<HackRF> = Vec::with_capacity(receivers.len());
+    let mut error: Option<Error> = None;
+    for receiver in receivers {
+        match receiver.stop() {
+            Ok(hackrf) => devices.push(hackrf),
+            Err((receiver, e)) => {
+                error.get_or_insert(e);
+                devices.push(receiver.into_device());
+            }
+        }
+    }
+    (SyncGroup { devices }, error)
+}
+
+fn stream_device(
+    receiver: &mut Receiver,
+    config: StreamConfig,
+    sender: &mpsc::SyncSender<Result<IqBlock, Error>>,
+    exit: &AtomicBool,
+) -> Result<(), Error> {
+    let mut stream: RxStream = receiver.rx_stream(config)?;
+    loop {
+        if exit.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        match stream.wait_for_trigger(TRIGGER_POLL) {
+            Ok(_) => break,
+            Err(Error::Usb(rusb::Error::Timeout)) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    for block in stream.iq_blocks() {
+        if exit.load(Ordering::Relaxed) || sender.send(Ok(block?)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,18 @@
 #[cfg(test)]
-use crate::freq_params;
+use crate::{
+    freq_params, BoardId, BoardKind, BoardRevision, Complex, CpldUpdate, DeviceInfo,
+    DeviceListEntry, Error, FirmwareImage, FreqPlan, GroupBlock, HackRF, HwSyncMode, IqBlock,
+    M0State, Max2837Registers, MockRecord, MockTransport, OperacakeGpioTest, OperacakeMode,
+    OperacakePort, OperacakeSchedule, PartIdSerial, PlatformInfo, Pll, Receiver,
+    RecordingTransport, ReplayTransport, Request, RfPathFilter, Rffc5071Path, Rffc5071Registers,
+    SerialNumber, Si5351cRegisters, Signal, SimulatedHackRF, StreamConfig, StreamStats, SweepBlock,
+    SweepStyle, Sweeper, SyncConfig, SyncGroup, SyncStream, TransceiverMode, TransferStatus,
+    Transmitter, Transport, TxStream,
+};
+#[cfg(test)]
+use rusb::Version;
+#[cfg(test)]
+use std::time::{Duration, SystemTime};
 #[test]
 fn nominal() {
     assert_eq!(freq_params(915_000_000), [0x93, 0x03, 0, 0, 0, 0, 0, 0]);
@@ -19,3 +32,1111 @@ fn min() {
 fn max() {
     assert_eq!(freq_params(u64::MAX), [0xFF; 8]);
 }
+
+#[test]
+fn board_kind() {
+    assert_eq!(BoardKind::from_usb_pid(0x604B), Some(BoardKind::Jawbreaker));
+    assert_eq!(BoardKind::from_usb_pid(0x6089), Some(BoardKind::HackRFOne));
+    assert_eq!(BoardKind::from_usb_pid(0xCC15), Some(BoardKind::Rad1o));
+    assert_eq!(BoardKind::from_usb_pid(0x0000), None);
+    assert_eq!(BoardKind::from_usb_pid(0x600B), None);
+    let dfu: DeviceListEntry = DeviceListEntry {
+        bus_number: 1,
+        port_numbers: vec![2, 1],
+        address: 5,
+        product_id: 0x600B,
+        details: Err(Error::Unsupported),
+    };
+    assert!(dfu.in_dfu_mode());
+    assert_eq!(dfu.bus_path(), "1-2.1");
+    assert_eq!(BoardId::from(4).kind(), Some(BoardKind::HackRFOneR9));
+    assert_eq!(BoardId::from(0xFE).kind(), None);
+
+    assert!(BoardKind::HackRFOne.capabilities().bias_tee);
+    assert!(!BoardKind::Rad1o
+        .capabilities()
+        .freq_range
+        .contains(&5_000_000_000));
+}
+
+#[test]
+fn board_id_and_revision() {
+    for id in 0..=u8::MAX {
+        assert_eq!(u8::from(BoardId::from(id)), id);
+    }
+    assert_eq!(BoardId::from(2).to_string(), "HackRF One");
+
+    assert!(BoardRevision::from(0x84).is_gsg());
+    assert_eq!(BoardRevision::from(0x84).to_string(), "r9");
+    assert_eq!(BoardRevision::from(0x04), BoardRevision::R9);
+    assert_eq!(BoardRevision::from(0xFF), BoardRevision::Unrecognized(0xFF));
+
+    let platform: PlatformInfo = PlatformInfo::from_bits(0b1010);
+    assert!(platform.supports(BoardKind::HackRFOne));
+    assert!(platform.supports(BoardKind::HackRFOneR9));
+    assert!(!platform.supports(BoardKind::Rad1o));
+    assert_eq!(platform.to_string(), "HackRF One, HackRF One r9");
+}
+
+#[test]
+fn serial_number() {
+    let mut buffer: [u8; 24] = [0; 24];
+    buffer[0] = 0x01;
+    buffer[4] = 0x02;
+    buffer[16..24].copy_from_slice(&[0xef, 0xbe, 0xad, 0xde, 0x78, 0x56, 0x34, 0x12]);
+    let part_id_serial: PartIdSerial = PartIdSerial::from_bytes(&buffer);
+    assert_eq!(part_id_serial.part_id, [1, 2]);
+    assert_eq!(
+        part_id_serial.serial.to_string(),
+        "0000000000000000deadbeef12345678"
+    );
+
+    let serial: SerialNumber = part_id_serial.serial.to_string().parse().unwrap();
+    assert_eq!(serial, part_id_serial.serial);
+    assert_eq!("deadbeef12345678".parse::<SerialNumber>().unwrap(), serial);
+    assert!("xyz".parse::<SerialNumber>().is_err());
+    assert!(format!("{:033}", 0).parse::<SerialNumber>().is_err());
+
+    let parsed: PartIdSerial = part_id_serial.to_string().parse().unwrap();
+    assert_eq!(parsed.serial, part_id_serial.serial);
+    assert_eq!(parsed.part_id, [0, 0]);
+    assert_eq!(parsed.to_string(), part_id_serial.to_string());
+
+    assert!(serial.matches("12345678"));
+    assert!(serial.matches("BEEF12345678"));
+    assert!(!serial.matches("1234567"));
+    assert!(!serial.matches(""));
+}
+
+#[test]
+fn iq_block() {
+    let block: IqBlock = IqBlock::from_bytes(1024, &[0x01, 0xFF, 0x80, 0x7F, 0x05]);
+    assert_eq!(block.offset, 512);
+    assert_eq!(
+        block.samples,
+        vec![Complex::new(1, -1), Complex::new(-128, 127)]
+    );
+}
+
+#[test]
+fn mock_control() {
+    let mock: MockTransport = MockTransport::new();
+    let mut hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    assert_eq!(hackrf.board_kind(), BoardKind::HackRFOne);
+    mock.clear_records();
+
+    mock.respond(Request::SetLnaGain, Ok(vec![1]));
+    hackrf.set_lna_gain(17).unwrap();
+    assert_eq!(hackrf.set_lna_gain(41), Err(Error::Argument));
+    hackrf.set_freq(915_000_000).unwrap();
+    assert_eq!(
+        mock.records(),
+        vec![
+            MockRecord::ControlIn {
+                request: Request::SetLnaGain,
+                value: 0,
+                index: 16,
+                length: 1,
+            },
+            MockRecord::ControlOut {
+                request: Request::SetFreq,
+                value: 0,
+                index: 0,
+                data: freq_params(915_000_000).to_vec(),
+            },
+        ]
+    );
+
+    hackrf.set_freq(7_250_000_000).unwrap();
+    assert_eq!(hackrf.set_freq(7_251_000_000), Err(Error::Argument));
+
+    mock.respond(Request::SetVgaGain, Ok(vec![0]));
+    assert_eq!(hackrf.set_vga_gain(20), Err(Error::Argument));
+    assert_eq!(hackrf.set_vga_gain(20), Err(Error::Usb(rusb::Error::Pipe)));
+}
+
+#[test]
+fn mock_device_info() {
+    let mock: MockTransport = MockTransport::new();
+    mock.set_device_version(Version::from_bcd(0x0104));
+    mock.respond(Request::BoardIdRead, Ok(vec![2]));
+    let hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+
+    let mut part_id_serial: Vec<u8> = vec![0; 24];
+    part_id_serial[20] = 0x42;
+    mock.respond(Request::BoardPartidSerialnoRead, Ok(part_id_serial));
+    mock.respond(Request::BoardIdRead, Ok(vec![2]));
+    let info: DeviceInfo = hackrf.device_info().unwrap();
+    assert_eq!(info.board_id, BoardId::HackRFOneOg);
+    assert_eq!(info.board_rev, None);
+    assert_eq!(info.serial_number.0, [0, 0, 0, 0x42]);
+
+    assert!(matches!(hackrf.board_rev(), Err(Error::Version { .. })));
+    mock.respond(Request::BoardPartidSerialnoRead, Ok(vec![0; 8]));
+    assert!(matches!(
+        hackrf.part_id_serial_read(),
+        Err(Error::ControlTransfer { actual: 8, .. })
+    ));
+}
+
+#[test]
+fn mock_rx_and_drop() {
+    let mock: MockTransport = MockTransport::new();
+    let mut receiver: Receiver = HackRF::with_transport(mock.clone())
+        .unwrap()
+        .enter_rx_mode()
+        .unwrap();
+    receiver.set_amp_enable(true).unwrap();
+
+    mock.push_bulk(Ok([0x01, 0xFF].repeat(256)));
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 2,
+        transfer_size: 512,
+    };
+    let mut blocks = receiver.rx_stream(config).unwrap().iq_blocks();
+    let block: IqBlock = blocks.next().unwrap().unwrap();
+    assert_eq!(block.offset, 0);
+    assert_eq!(block.samples, vec![Complex::new(1, -1); 256]);
+    assert_eq!(
+        blocks.next().unwrap().unwrap_err(),
+        Error::Usb(rusb::Error::Timeout)
+    );
+    drop(blocks);
+
+    mock.clear_records();
+    drop(receiver);
+    assert_eq!(
+        mock.records(),
+        vec![
+            MockRecord::ControlOut {
+                request: Request::SetTransceiverMode,
+                value: 0,
+                index: 0,
+                data: vec![],
+            },
+            MockRecord::ControlOut {
+                request: Request::AmpEnable,
+                value: 0,
+                index: 0,
+                data: vec![],
+            },
+            MockRecord::ReleaseInterface(0),
+        ]
+    );
+}
+
+#[cfg(test)]
+fn tone_phase_step(receiver: &mut Receiver) -> f64 {
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 1,
+        transfer_size: 512,
+    };
+    let block: IqBlock = receiver
+        .rx_stream(config)
+        .unwrap()
+        .iq_blocks()
+        .next()
+        .unwrap()
+        .unwrap();
+    let samples: Vec<Complex<f64>> = block
+        .samples
+        .iter()
+        .map(|s| Complex::new(f64::from(s.re), f64::from(s.im)))
+        .collect();
+    samples
+        .windows(2)
+        .map(|w| w[1] * w[0].conj())
+        .sum::<Complex<f64>>()
+        .arg()
+}
+
+#[test]
+fn simulator() {
+    let sim: SimulatedHackRF = SimulatedHackRF::new();
+    sim.add_signal(Signal::Tone {
+        freq_hz: 100_250_000,
+        amplitude: 0.5,
+    });
+    let mut hackrf: HackRF = HackRF::with_transport(sim.clone()).unwrap();
+    hackrf.set_sample_rate(2_000_000, 1).unwrap();
+    hackrf.set_freq(100_000_000).unwrap();
+    hackrf.set_lna_gain(8).unwrap();
+    assert_eq!(sim.sample_rate(), 2_000_000.0);
+    assert_eq!(sim.lna_gain(), 8);
+    hackrf.set_lna_gain(0).unwrap();
+
+    let mut receiver: Receiver = hackrf.enter_rx_mode().unwrap();
+    assert_eq!(sim.mode(), TransceiverMode::Receive);
+    let step: f64 = tone_phase_step(&mut receiver);
+    assert!((step - std::f64::consts::FRAC_PI_4).abs() < 0.01);
+
+    receiver.set_freq(100_500_000).unwrap();
+    let step: f64 = tone_phase_step(&mut receiver);
+    assert!((step + std::f64::consts::FRAC_PI_4).abs() < 0.01);
+
+    let mut transmitter: Transmitter = receiver.stop().unwrap().enter_tx_mode().unwrap();
+    transmitter.tx(vec![1, 2, 0xFF, 4]).unwrap();
+    assert_eq!(
+        sim.take_tx_samples(),
+        vec![Complex::new(1, 2), Complex::new(-1, 4)]
+    );
+    drop(transmitter);
+    assert_eq!(sim.mode(), TransceiverMode::Off);
+}
+
+#[test]
+fn record_and_replay() {
+    fn session(hackrf: &mut HackRF) -> Result<String, Error> {
+        hackrf.set_sample_rate_auto(10_000_000)?;
+        hackrf.set_freq(433_920_000)?;
+        hackrf.set_lna_gain(16)?;
+        hackrf.version()
+    }
+
+    assert_eq!(Request::try_from(16), Ok(Request::SetFreq));
+    assert_eq!(Request::try_from(13), Err(Error::Argument));
+
+    let path: std::path::PathBuf =
+        std::env::temp_dir().join(format!("libhackrf-session-{}.txt", std::process::id()));
+    let recording: RecordingTransport<SimulatedHackRF> =
+        RecordingTransport::new(SimulatedHackRF::new(), &path).unwrap();
+    let mut hackrf: HackRF = HackRF::with_transport(recording).unwrap();
+    assert_eq!(session(&mut hackrf), Ok("simulated".into()));
+    drop(hackrf);
+
+    let replay: ReplayTransport = ReplayTransport::open(&path).unwrap();
+    let mut hackrf: HackRF = HackRF::with_transport(replay.clone()).unwrap();
+    assert_eq!(session(&mut hackrf), Ok("simulated".into()));
+    assert_eq!(replay.remaining(), 0);
+
+    let replay: ReplayTransport = ReplayTransport::open(&path).unwrap();
+    let mut hackrf: HackRF = HackRF::with_transport(replay.clone()).unwrap();
+    hackrf.set_sample_rate_auto(10_000_000).unwrap();
+    assert_eq!(hackrf.set_freq(915_000_000), Err(Error::Replay { line: 5 }));
+
+    // a payload longer than the read it answers is rejected up front
+    let mut session: String = std::fs::read_to_string(&path).unwrap();
+    let line: usize = session.lines().count() + 1;
+    session.push_str("bulk_in 129 2 ok 010203\n");
+    std::fs::write(&path, session).unwrap();
+    assert_eq!(
+        ReplayTransport::open(&path).err(),
+        Some(Error::Replay { line })
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn max2837() {
+    let mock: MockTransport = MockTransport::new();
+    let mut hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    assert_eq!(hackrf.max2837_read(32), Err(Error::Argument));
+    assert_eq!(hackrf.max2837_write(5, 0x400), Err(Error::Argument));
+
+    mock.respond(Request::Max2837Read, Ok(vec![0x2A, 0x01]));
+    assert_eq!(hackrf.max2837_read(3), Ok(0x12A));
+    mock.clear_records();
+    hackrf.max2837_write(5, 0x18).unwrap();
+    assert_eq!(
+        mock.records(),
+        vec![MockRecord::ControlOut {
+            request: Request::Max2837Write,
+            value: 0x18,
+            index: 5,
+            data: vec![],
+        }]
+    );
+
+    let mut registers: [u16; 32] = [0; 32];
+    registers[1] = 3 << 2;
+    registers[5] = 0x18;
+    registers[8] = 0x0D;
+    registers[16] = 1;
+    registers[29] = 0b01_0101;
+    let registers: Max2837Registers = registers.into();
+    assert!(registers.chip_enabled());
+    assert_eq!(registers.lna_gain(), Some(16));
+    assert_eq!(registers.vga_gain(), 14);
+    assert_eq!(registers.txvga_gain(), 5);
+    assert_eq!(registers.lpf_bandwidth(), 20_000_000);
+    assert!(registers
+        .to_string()
+        .starts_with("[ 0] -> 0x000\n[ 1] -> 0x00c\n"));
+}
+
+#[test]
+fn si5351c() {
+    let mut registers: [u8; 256] = [0; 256];
+    registers[0] = 0x10;
+    registers[3] = 0xF7;
+    registers[16] = 0x80;
+    registers[19] = 0x0F;
+    registers[26..34].copy_from_slice(&[0, 1, 0, 0x0E, 0, 0, 0, 0]);
+    registers[66..74].copy_from_slice(&[0, 1, 0, 0x26, 0, 0, 0, 0]);
+    let registers: Si5351cRegisters = Si5351cRegisters(registers);
+
+    assert!(registers.status().clkin_lost);
+    assert!(registers.status().pll_a_locked);
+    assert_eq!(registers.clock_control(3).drive_ma, 8);
+    assert_eq!(registers.pll_frequency(Pll::A), Some(800_000_000.0));
+    assert_eq!(registers.clkout_frequency(), Some(10_000_000.0));
+    assert_eq!(registers.clock_frequency(0), None);
+    assert!(!registers.output_enabled(0));
+}
+
+#[test]
+fn rffc5071() {
+    let mock: MockTransport = MockTransport::new();
+    let mut hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    assert_eq!(hackrf.rffc5071_read(31), Err(Error::Argument));
+    assert_eq!(hackrf.rffc5071_write(31, 0), Err(Error::Argument));
+
+    let mut registers: [u16; 31] = [0; 31];
+    registers[0x0F] = (30 << 7) | (2 << 4) | (2 << 2);
+    registers[0x10] = 0x8000;
+    registers[0x15] = 0x6000;
+    let registers: Rffc5071Registers = Rffc5071Registers(registers);
+    assert!(registers.enabled());
+    assert_eq!(registers.active_path(), Rffc5071Path::Two);
+    assert_eq!(registers.synth(Rffc5071Path::Two).lodiv, 4);
+    assert_eq!(registers.synth(Rffc5071Path::Two).fbkdiv, 4);
+    assert_eq!(registers.lo_frequency(), 1_220_000_000.0);
+}
+
+#[test]
+fn spiflash() {
+    let mock: MockTransport = MockTransport::new();
+    let mut hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    assert_eq!(
+        hackrf.spiflash_write(0xFFFFF, &[0, 0]),
+        Err(Error::Argument)
+    );
+    assert_eq!(hackrf.spiflash_read(1, usize::MAX), Err(Error::Argument));
+
+    mock.clear_records();
+    let mut reports: Vec<(usize, usize)> = Vec::new();
+    hackrf
+        .spiflash_write_with_progress(0x1_00F0, &[0xAA; 300], |done, total| {
+            reports.push((done, total))
+        })
+        .unwrap();
+    assert_eq!(reports, vec![(16, 300), (272, 300), (300, 300)]);
+    let writes: Vec<(u16, u16, usize)> = mock
+        .records()
+        .into_iter()
+        .map(|record| match record {
+            MockRecord::ControlOut {
+                value, index, data, ..
+            } => (value, index, data.len()),
+            record => panic!("unexpected {:?}", record),
+        })
+        .collect();
+    assert_eq!(
+        writes,
+        vec![(1, 0x00F0, 16), (1, 0x0100, 256), (1, 0x0200, 28)]
+    );
+
+    mock.respond(Request::SpiflashRead, Ok(vec![1; 256]));
+    mock.respond(Request::SpiflashRead, Ok(vec![2; 4]));
+    assert_eq!(
+        hackrf.spiflash_read(0, 260),
+        Ok([vec![1; 256], vec![2; 4]].concat())
+    );
+}
+
+#[test]
+fn firmware_update() {
+    assert_eq!(FirmwareImage::from_bytes(vec![]), Err(Error::Argument));
+    assert_eq!(
+        FirmwareImage::from_bytes(vec![0; 0x10_0001]),
+        Err(Error::Argument)
+    );
+    let mut data: Vec<u8> = vec![0x55; 300];
+    data[100..110].copy_from_slice(b"HackRF One");
+    let image: FirmwareImage = FirmwareImage::from_bytes(data.clone()).unwrap();
+    assert!(image.targets(BoardKind::HackRFOneR9));
+    assert!(!image.targets(BoardKind::Rad1o));
+
+    let mock: MockTransport = MockTransport::new();
+    mock.respond(Request::BoardIdRead, Ok(vec![2]));
+    mock.respond(Request::BoardIdRead, Ok(vec![2]));
+    mock.respond(Request::SpiflashRead, Ok(data[..256].to_vec()));
+    mock.respond(Request::SpiflashRead, Ok(vec![0xFF; 44]));
+    let hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    let (hackrf, error) = hackrf.update_firmware(&image).unwrap_err();
+    assert_eq!(error, Error::Verification);
+    assert!(!mock.records().iter().any(|record| matches!(
+        record,
+        MockRecord::ControlOut {
+            request: Request::Reset,
+            ..
+        }
+    )));
+
+    // the same handle retries the update
+    mock.respond(Request::BoardIdRead, Ok(vec![2]));
+    mock.respond(Request::SpiflashRead, Ok(data[..256].to_vec()));
+    mock.respond(Request::SpiflashRead, Ok(data[256..].to_vec()));
+    mock.clear_records();
+    hackrf.update_firmware(&image).unwrap();
+    let requests: Vec<Request> = mock
+        .records()
+        .into_iter()
+        .filter_map(|record| match record {
+            MockRecord::ControlIn { request, .. } | MockRecord::ControlOut { request, .. } => {
+                Some(request)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        requests,
+        vec![
+            Request::BoardIdRead,
+            Request::SpiflashErase,
+            Request::SpiflashWrite,
+            Request::SpiflashWrite,
+            Request::SpiflashRead,
+            Request::SpiflashRead,
+            Request::Reset,
+        ]
+    );
+}
+
+#[test]
+fn cpld_update() {
+    let mock: MockTransport = MockTransport::new();
+    let hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    mock.respond(Request::BoardPartidSerialnoRead, Ok(vec![0; 24]));
+    mock.respond(Request::CpldChecksum, Ok(vec![0x78, 0x56, 0x34, 0x12]));
+    mock.clear_records();
+
+    let mut reports: Vec<usize> = Vec::new();
+    let update: CpldUpdate = hackrf
+        .cpld_update_with_progress(&[0xAB; 600], |done, _| reports.push(done))
+        .unwrap();
+    assert_eq!(update.previous_checksum, 0x1234_5678);
+    assert_eq!(reports, vec![512, 600]);
+
+    let records: Vec<MockRecord> = mock.records();
+    assert_eq!(
+        records[2],
+        MockRecord::ControlOut {
+            request: Request::SetTransceiverMode,
+            value: 4,
+            index: 0,
+            data: vec![],
+        }
+    );
+    assert_eq!(records[3], MockRecord::ClaimInterface(0));
+    assert_eq!(
+        records[5],
+        MockRecord::BulkOut {
+            endpoint: 0x02,
+            data: [vec![0xAB; 88], vec![0; 424]].concat(),
+        }
+    );
+    assert_eq!(records[6], MockRecord::ReleaseInterface(0));
+    assert_eq!(records.len(), 7);
+
+    let hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    mock.respond(Request::CpldChecksum, Ok(vec![1, 0, 0, 0]));
+    assert_eq!(update.verify(&hackrf, 2), Err(Error::Verification));
+    mock.respond(Request::CpldChecksum, Ok(vec![2, 0, 0, 0]));
+    assert_eq!(update.verify(&hackrf, 2), Ok(()));
+    assert!(update.changed(2));
+}
+
+#[test]
+fn sweep() {
+    let sim: SimulatedHackRF = SimulatedHackRF::new();
+    let mut hackrf: HackRF = HackRF::with_transport(sim.clone()).unwrap();
+    assert_eq!(
+        hackrf.init_sweep(&[(100, 120)], 1000, 10_000_000, 0, SweepStyle::Linear),
+        Err(Error::Argument)
+    );
+    assert_eq!(
+        hackrf.init_sweep(&[(120, 100)], 16384, 10_000_000, 0, SweepStyle::Linear),
+        Err(Error::Argument)
+    );
+    hackrf
+        .init_sweep(
+            &[(100, 120)],
+            16384,
+            10_000_000,
+            2_500_000,
+            SweepStyle::Linear,
+        )
+        .unwrap();
+
+    let mut sweeper: Sweeper = hackrf.enter_sweep_mode().unwrap();
+    assert_eq!(sim.mode(), TransceiverMode::RxSweep);
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 1,
+        transfer_size: 32768,
+    };
+    let blocks: Vec<SweepBlock> = sweeper
+        .blocks(config)
+        .unwrap()
+        .take(4)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let centers: Vec<u64> = blocks.iter().map(|block| block.center_hz).collect();
+    assert_eq!(
+        centers,
+        vec![102_500_000, 112_500_000, 102_500_000, 112_500_000]
+    );
+    assert_eq!(blocks[0].samples.len(), 8184);
+    assert!(sweeper.blocks(StreamConfig::default()).is_ok());
+    assert!(sweeper
+        .blocks(StreamConfig {
+            transfer_count: 1,
+            transfer_size: 512,
+        })
+        .is_err());
+    sweeper.stop().unwrap();
+}
+
+#[test]
+fn operacake() {
+    let mock: MockTransport = MockTransport::new();
+    let mut hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+
+    mock.respond(
+        Request::OperacakeGetBoards,
+        Ok(vec![0, 3, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+    );
+    assert_eq!(hackrf.operacake_boards(), Ok(vec![0, 3]));
+
+    assert_eq!(
+        hackrf.operacake_set_ports(0, OperacakePort::A1, OperacakePort::A2),
+        Err(Error::Argument)
+    );
+    assert_eq!(
+        hackrf.operacake_set_ranges(&[(2400, 100, OperacakePort::A1)]),
+        Err(Error::Argument)
+    );
+    mock.clear_records();
+    hackrf
+        .operacake_set_ports(3, OperacakePort::B2, OperacakePort::A4)
+        .unwrap();
+    hackrf
+        .operacake_set_ranges(&[
+            (100, 600, OperacakePort::A1),
+            (2400, 2500, OperacakePort::B3),
+        ])
+        .unwrap();
+    assert_eq!(
+        mock.records(),
+        vec![
+            MockRecord::ControlOut {
+                request: Request::OperacakeSetPorts,
+                value: 3,
+                index: 0x0305,
+                data: vec![],
+            },
+            MockRecord::ControlOut {
+                request: Request::OperacakeSetRanges,
+                value: 0,
+                index: 0,
+                data: vec![0x00, 0x64, 0x02, 0x58, 0, 0x09, 0x60, 0x09, 0xC4, 6],
+            },
+        ]
+    );
+
+    mock.respond(Request::OperacakeGpioTest, Ok(vec![0x02, 0x10]));
+    let test: OperacakeGpioTest = hackrf.operacake_gpio_test(0).unwrap();
+    assert!(!test.passed());
+    let failed: Vec<&str> = test
+        .pins()
+        .unwrap()
+        .iter()
+        .filter(|pin| !pin.passed())
+        .map(|pin| pin.name)
+        .collect();
+    assert_eq!(failed, vec!["u2ctrl1", "u1ctrl"]);
+    assert_eq!(OperacakeGpioTest(0xFFFF).pins(), None);
+}
+
+#[test]
+fn operacake_schedule() {
+    assert_eq!(OperacakeSchedule::new(&[]), Err(Error::Argument));
+    assert_eq!(
+        OperacakeSchedule::new(&[(OperacakePort::A1, 0)]),
+        Err(Error::Argument)
+    );
+    let schedule: OperacakeSchedule =
+        OperacakeSchedule::new(&[(OperacakePort::A1, 100), (OperacakePort::A3, 50)]).unwrap();
+    assert_eq!(schedule.period(), 150);
+    assert_eq!(schedule.port_at(149), OperacakePort::A3);
+    assert_eq!(schedule.port_at(150), OperacakePort::A1);
+
+    let mock: MockTransport = MockTransport::new();
+    let mut hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    mock.clear_records();
+    hackrf.operacake_set_dwell_times(schedule.dwells()).unwrap();
+    assert_eq!(
+        mock.records(),
+        vec![MockRecord::ControlOut {
+            request: Request::OperacakeSetDwellTimes,
+            value: 0,
+            index: 0,
+            data: vec![100, 0, 0, 0, 0, 50, 0, 0, 0, 2],
+        }]
+    );
+    hackrf.operacake_set_mode(0, OperacakeMode::Time).unwrap();
+
+    let mut receiver: Receiver = hackrf.enter_rx_mode().unwrap();
+    mock.push_bulk(Ok(vec![0; 512]));
+    mock.push_bulk(Ok(vec![0; 512]));
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 2,
+        transfer_size: 512,
+    };
+    let mut blocks = receiver.rx_stream(config).unwrap().iq_blocks();
+    let ports: Vec<(OperacakePort, std::ops::Range<usize>)> = blocks
+        .nth(1)
+        .unwrap()
+        .unwrap()
+        .ports
+        .into_iter()
+        .map(|segment| (segment.port, segment.samples))
+        .collect();
+    // the second block starts at sample 256, six samples into the A3 dwell
+    assert_eq!(
+        ports,
+        vec![
+            (OperacakePort::A3, 0..44),
+            (OperacakePort::A1, 44..144),
+            (OperacakePort::A3, 144..194),
+            (OperacakePort::A1, 194..256),
+        ]
+    );
+    drop(blocks);
+
+    // leaving time mode drops the schedule, and without a board in time mode
+    // no ports are attached
+    let mut hackrf: HackRF = receiver.stop().unwrap();
+    hackrf.operacake_set_mode(0, OperacakeMode::Manual).unwrap();
+    assert_eq!(hackrf.operacake_schedule(), None);
+    hackrf.operacake_set_dwell_times(schedule.dwells()).unwrap();
+    let mut receiver: Receiver = hackrf.enter_rx_mode().unwrap();
+    mock.push_bulk(Ok(vec![0; 512]));
+    let mut blocks = receiver.rx_stream(config).unwrap().iq_blocks();
+    assert!(blocks.next().unwrap().unwrap().ports.is_empty());
+}
+
+#[test]
+fn freq_plan() {
+    for hz in [
+        0,
+        915_000_000,
+        2_169_999_999,
+        2_450_000_000,
+        3_000_000_000,
+        7_250_000_000,
+    ] {
+        let plan: FreqPlan = FreqPlan::for_freq(hz).unwrap();
+        assert_eq!(plan.rf_hz(), hz);
+    }
+    assert_eq!(
+        FreqPlan::for_freq(915_000_000),
+        Ok(FreqPlan {
+            if_hz: 2_519_000_000,
+            lo_hz: 3_434_000_000,
+            path: RfPathFilter::LowPass,
+        })
+    );
+    assert_eq!(
+        FreqPlan::for_freq(5_800_000_000),
+        Ok(FreqPlan {
+            if_hz: 2_577_000_000,
+            lo_hz: 3_223_000_000,
+            path: RfPathFilter::HighPass,
+        })
+    );
+    assert_eq!(FreqPlan::for_freq(7_251_000_000), Err(Error::Argument));
+
+    let simulator: SimulatedHackRF = SimulatedHackRF::new();
+    let mut hackrf: HackRF = HackRF::with_transport(simulator.clone()).unwrap();
+    assert_eq!(
+        hackrf.set_freq_explicit(2_000_000_000, 3_000_000_000, RfPathFilter::LowPass),
+        Err(Error::Argument)
+    );
+    assert_eq!(
+        hackrf.set_freq_explicit(2_400_000_000, 50_000_000, RfPathFilter::HighPass),
+        Err(Error::Argument)
+    );
+    // the same frequency with the IF moved 30 MHz and the LO following it
+    hackrf
+        .set_freq_explicit(2_549_000_000, 3_464_000_000, RfPathFilter::LowPass)
+        .unwrap();
+    assert_eq!(simulator.freq(), 915_000_000);
+    hackrf
+        .set_freq_explicit(2_450_000_000, 0, RfPathFilter::Bypass)
+        .unwrap();
+    assert_eq!(simulator.freq(), 2_450_000_000);
+}
+
+#[test]
+fn armed_rx() {
+    let simulator: SimulatedHackRF = SimulatedHackRF::new();
+    let mut hackrf: HackRF = HackRF::with_transport(simulator.clone()).unwrap();
+    assert_eq!(hackrf.sample_rate(), None);
+    assert_eq!(hackrf.set_sample_rate(2_000_000, 0), Err(Error::Argument));
+    hackrf.set_sample_rate(2_000_000, 1).unwrap();
+    assert_eq!(hackrf.sample_rate(), Some(2_000_000.0));
+
+    let mut receiver: Receiver = hackrf.enter_armed_rx_mode().unwrap();
+    assert!(simulator.hw_sync());
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 2,
+        transfer_size: 4096,
+    };
+    let mut stream = receiver.rx_stream(config).unwrap();
+    assert_eq!(
+        stream.wait_for_trigger(Duration::from_millis(10)),
+        Err(Error::Usb(rusb::Error::Timeout))
+    );
+
+    simulator.trigger();
+    let before: SystemTime = SystemTime::now();
+    let triggered: SystemTime = stream.wait_for_trigger(Duration::from_millis(10)).unwrap();
+    // 2048 samples at 2 Msps took 1.024 ms to capture
+    assert!(triggered <= before);
+    assert!(before.duration_since(triggered).unwrap() < Duration::from_millis(100));
+
+    // the transfer that was waited for is the first one handed out
+    let transfer = stream.next_transfer().unwrap();
+    assert_eq!((transfer.sequence, transfer.data.len()), (0, 4096));
+    assert_eq!(
+        stream.wait_for_trigger(Duration::from_millis(10)),
+        Err(Error::Argument)
+    );
+    drop(stream);
+
+    receiver.stop().unwrap();
+    assert!(!simulator.hw_sync());
+
+    // a dropped receiver disarms too
+    let receiver: Receiver = HackRF::with_transport(simulator.clone())
+        .unwrap()
+        .enter_armed_rx_mode()
+        .unwrap();
+    assert!(simulator.hw_sync());
+    drop(receiver);
+    assert!(!simulator.hw_sync());
+
+    // and so does a board that fails to start
+    let mock: MockTransport = MockTransport::new();
+    let hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+    mock.clear_records();
+    mock.respond(Request::SetTransceiverMode, Err(Error::Busy));
+    let (_hackrf, error) = hackrf.enter_armed_rx_mode().unwrap_err();
+    assert_eq!(error, Error::Busy);
+    let sync: Vec<u16> = mock
+        .records()
+        .into_iter()
+        .filter_map(|record| match record {
+            MockRecord::ControlOut {
+                request: Request::SetHwSyncMode,
+                value,
+                ..
+            } => Some(value),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sync, vec![HwSyncMode::On.into(), HwSyncMode::Off.into()]);
+}
+
+#[test]
+fn sync_group() {
+    let simulators: Vec<SimulatedHackRF> = vec![SimulatedHackRF::new(), SimulatedHackRF::new()];
+    let devices: Vec<HackRF> = simulators
+        .iter()
+        .map(|simulator| HackRF::with_transport(simulator.clone()).unwrap())
+        .collect();
+    assert_eq!(SyncGroup::from_devices(vec![]).err(), Some(Error::Argument));
+    let mut group: SyncGroup = SyncGroup::from_devices(devices).unwrap();
+    group
+        .configure(&SyncConfig {
+            freq_hz: 433_920_000,
+            sample_rate: 2_000_000,
+            lna_gain: 16,
+            vga_gain: 20,
+            amp_enable: false,
+        })
+        .unwrap();
+    assert!(simulators
+        .iter()
+        .all(|simulator| simulator.freq() == 433_920_000));
+
+    // the second board loses its first transfer after the trigger
+    simulators[1].lose_transfer();
+    let mut stream: SyncStream = group
+        .start(StreamConfig {
+            transfer_count: 2,
+            transfer_size: 4096,
+        })
+        .unwrap();
+    assert!(simulators.iter().all(|simulator| simulator.hw_sync()));
+    stream.set_timeout(Duration::from_millis(10));
+    assert_eq!(
+        stream.next().unwrap().unwrap_err(),
+        Error::Usb(rusb::Error::Timeout)
+    );
+    stream.set_timeout(Duration::from_secs(1));
+    simulators.iter().for_each(|simulator| simulator.trigger());
+
+    let first: GroupBlock = stream.next().unwrap().unwrap();
+    assert_eq!(first.offset, 0);
+    assert!(first.dropped);
+    assert_eq!(first.samples[1], vec![Complex::new(0, 0); 2048]);
+    let second: GroupBlock = stream.next().unwrap().unwrap();
+    assert_eq!(second.offset, 2048);
+    assert!(!second.dropped);
+    assert_eq!(second.samples[0], second.samples[1]);
+    assert!(stream.dropped());
+
+    let group: SyncGroup = stream.stop().unwrap();
+    assert_eq!(group.devices().len(), 2);
+    assert!(simulators.iter().all(|simulator| !simulator.hw_sync()));
+}
+
+#[test]
+fn m0_state() {
+    let mock: MockTransport = MockTransport::new();
+    let mut receiver: Receiver = HackRF::with_transport(mock.clone())
+        .unwrap()
+        .enter_rx_mode()
+        .unwrap();
+    let mut state: Vec<u8> = vec![0; 40];
+    state[4] = 1;
+    state[16..20].copy_from_slice(&3u32.to_le_bytes());
+    state[20..24].copy_from_slice(&4096u32.to_le_bytes());
+    mock.respond(Request::GetM0State, Ok(state));
+
+    let stream = receiver.rx_stream(StreamConfig::default()).unwrap();
+    let state: M0State = stream.m0_state().unwrap();
+    assert_eq!(
+        (
+            state.active_mode,
+            state.num_shortfalls,
+            state.longest_shortfall
+        ),
+        (1, 3, 4096)
+    );
+    assert_eq!(stream.stats().overruns, 0);
+}
+
+#[test]
+fn tx_underrun_and_flush() {
+    let mock: MockTransport = MockTransport::new();
+    let mut transmitter: Transmitter = HackRF::with_transport(mock.clone())
+        .unwrap()
+        .enter_tx_mode()
+        .unwrap();
+    mock.clear_records();
+
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 2,
+        transfer_size: 512,
+    };
+    let mut fills = vec![3, 512].into_iter();
+    let mut stream: TxStream = transmitter.tx_stream(config).unwrap();
+    let stats: StreamStats = stream
+        .transmit(|buffer: &mut [u8]| {
+            let n: usize = fills.next()?;
+            buffer[..n].fill(0x7F);
+            Some(n)
+        })
+        .unwrap();
+    assert_eq!(stats.underruns, 1);
+    assert_eq!(stats.transfers, 3);
+
+    let sent: Vec<Vec<u8>> = mock
+        .records()
+        .into_iter()
+        .filter_map(|record| match record {
+            MockRecord::BulkOut { data, .. } => Some(data),
+            _ => None,
+        })
+        .collect();
+    let mut short: Vec<u8> = vec![0; 512];
+    short[..3].fill(0x7F);
+    assert_eq!(sent, vec![short, vec![0x7F; 512], vec![0; 512]]);
+}
+
+#[test]
+fn bulk_stream_in_flight() {
+    let mock: MockTransport = MockTransport::new();
+    let mut stream = mock.bulk_stream(0x81, StreamConfig::default()).unwrap();
+    stream.submit(0, 512).unwrap();
+    assert_eq!(stream.submit(0, 512), Err(Error::Busy));
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        stream.buffer_mut(0)[0] = 1;
+    }))
+    .is_err());
+
+    mock.push_bulk(Err(Error::Usb(rusb::Error::Pipe)));
+    stream.wait(0, Duration::from_millis(10)).unwrap();
+    assert_eq!(stream.status(0), Ok((TransferStatus::Lost, 0)));
+    assert_eq!(
+        stream.buffer(0).len(),
+        StreamConfig::default().transfer_size
+    );
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn iq_stream() {
+    let mock: MockTransport = MockTransport::new();
+    let receiver: Receiver = HackRF::with_transport(mock.clone())
+        .unwrap()
+        .enter_rx_mode()
+        .unwrap();
+    mock.push_bulk(Ok([0x01, 0xFF].repeat(256)));
+    mock.push_bulk(Ok([0x02, 0xFE].repeat(256)));
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 2,
+        transfer_size: 512,
+    };
+
+    let mut blocks = futures::executor::block_on_stream(receiver.into_iq_stream(config));
+    let first: IqBlock = blocks.next().unwrap().unwrap();
+    assert_eq!(first.offset, 0);
+    assert_eq!(first.samples, vec![Complex::new(1, -1); 256]);
+    let second: IqBlock = blocks.next().unwrap().unwrap();
+    assert_eq!(second.offset, 256);
+    assert_eq!(second.samples, vec![Complex::new(2, -2); 256]);
+
+    // stopping closes the channel and hands the receiver back
+    let receiver: Receiver = blocks.into_inner().stop();
+    mock.clear_records();
+    receiver.stop().unwrap();
+    assert!(mock.records().contains(&MockRecord::ReleaseInterface(0)));
+}
+
+#[test]
+fn failed_mode_change() {
+    let mock: MockTransport = MockTransport::new();
+    let hackrf: HackRF = HackRF::with_transport(mock.clone()).unwrap();
+
+    // a refused mode change hands the device back
+    mock.respond(
+        Request::SetTransceiverMode,
+        Err(Error::Usb(rusb::Error::Pipe)),
+    );
+    let (hackrf, error) = hackrf.enter_rx_mode().unwrap_err();
+    assert_eq!(error, Error::Usb(rusb::Error::Pipe));
+    let (hackrf, error) = hackrf.enter_sweep_mode().unwrap_err();
+    assert_eq!(error, Error::Argument);
+    let receiver: Receiver = hackrf.enter_rx_mode().unwrap();
+
+    // and so does a refused stop, which can then be retried
+    mock.clear_records();
+    mock.respond(
+        Request::SetTransceiverMode,
+        Err(Error::Usb(rusb::Error::Pipe)),
+    );
+    let (receiver, error) = receiver.stop().unwrap_err();
+    assert_eq!(error, Error::Usb(rusb::Error::Pipe));
+    let hackrf: HackRF = receiver.stop().unwrap();
+    assert_eq!(
+        mock.records(),
+        vec![
+            MockRecord::ReleaseInterface(0),
+            MockRecord::ControlOut {
+                request: Request::SetTransceiverMode,
+                value: 0,
+                index: 0,
+                data: vec![],
+            },
+            MockRecord::ControlOut {
+                request: Request::SetTransceiverMode,
+                value: 0,
+                index: 0,
+                data: vec![],
+            },
+        ]
+    );
+    hackrf.enter_tx_mode().unwrap();
+}
+
+#[test]
+fn sync_group_retry() {
+    let simulators: Vec<SimulatedHackRF> = vec![SimulatedHackRF::new(), SimulatedHackRF::new()];
+    let devices: Vec<HackRF> = simulators
+        .iter()
+        .map(|simulator| HackRF::with_transport(simulator.clone()).unwrap())
+        .collect();
+    let group: SyncGroup = SyncGroup::from_devices(devices).unwrap();
+    let mut stream: SyncStream = group
+        .start(StreamConfig {
+            transfer_count: 2,
+            transfer_size: 4096,
+        })
+        .unwrap();
+
+    // only the first board sees the trigger, so the second lane times out
+    stream.set_timeout(Duration::from_millis(50));
+    simulators[0].trigger();
+    assert_eq!(
+        stream.next().unwrap().unwrap_err(),
+        Error::Usb(rusb::Error::Timeout)
+    );
+
+    // a retry picks up where the first lane left off
+    simulators[1].trigger();
+    stream.set_timeout(Duration::from_secs(1));
+    for offset in [0, 2048, 4096] {
+        let block: GroupBlock = stream.next().unwrap().unwrap();
+        assert_eq!(block.offset, offset);
+        assert!(!block.dropped);
+        assert_eq!(block.samples[0], block.samples[1]);
+    }
+    assert!(!stream.dropped());
+    stream.stop().unwrap();
+}
+
+#[test]
+fn sync_group_failures() {
+    let mocks: Vec<MockTransport> = vec![MockTransport::new(), MockTransport::new()];
+    let open_group = || {
+        mocks[1].respond(Request::GetClkinStatus, Ok(vec![1]));
+        let devices: Vec<HackRF> = mocks
+            .iter()
+            .map(|mock| HackRF::with_transport(mock.clone()).unwrap())
+            .collect();
+        SyncGroup::from_devices(devices).unwrap()
+    };
+    let config: StreamConfig = StreamConfig {
+        transfer_count: 2,
+        transfer_size: 4096,
+    };
+
+    // the second board fails to arm: the first is disarmed, both come back
+    mocks[1].respond(Request::SetTransceiverMode, Err(Error::Busy));
+    mocks[0].clear_records();
+    let (group, error) = open_group().start(config).err().unwrap();
+    assert_eq!(error, Error::Busy);
+    assert_eq!(group.devices().len(), 2);
+    assert!(mocks[0].records().contains(&MockRecord::ControlOut {
+        request: Request::SetHwSyncMode,
+        value: HwSyncMode::Off.into(),
+        index: 0,
+        data: vec![],
+    }));
+    drop(group);
+
+    // the first board fails to stop: the second is still stopped, both come back
+    let stream: SyncStream = open_group().start(config).unwrap();
+    mocks[0].respond(Request::SetTransceiverMode, Err(Error::Busy));
+    mocks[1].clear_records();
+    let (group, error) = stream.stop().unwrap_err();
+    assert_eq!(error, Error::Busy);
+    assert_eq!(group.devices().len(), 2);
+    assert!(mocks[1]
+        .records()
+        .contains(&MockRecord::ReleaseInterface(0)));
+}
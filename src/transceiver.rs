@@ -0,0 +1,181 @@
+use crate::constants::*;
+#[cfg(feature = "async")]
+use crate::iq::IqStream;
+use crate::stream::{RxStream, StreamConfig, TxStream};
+use crate::sweep::{SweepBlocks, SWEEP_BLOCK_SIZE};
+use crate::{Error, HackRF};
+
+// A device in receive mode. It owns the claimed interface, so only receiving
+// and retuning are possible until `stop` hands the idle device back. If the
+// board refuses to stop, the receiver comes back with the error instead.
+#[derive(Debug)]
+pub struct Receiver {
+    hackrf: HackRF,
+}
+
+impl Receiver {
+    pub(crate) fn new(hackrf: HackRF) -> Receiver {
+        Receiver { hackrf }
+    }
+
+    pub fn device(&self) -> &HackRF {
+        &self.hackrf
+    }
+
+    // Keeps the device of a receiver that failed to stop; its flags still
+    // tell `Drop` what to switch off.
+    pub(crate) fn into_device(self) -> HackRF {
+        self.hackrf
+    }
+
+    pub fn set_freq(&mut self, hz: u64) -> Result<(), Error> {
+        self.hackrf.set_freq(hz)
+    }
+
+    pub fn set_amp_enable(&mut self, en: bool) -> Result<(), Error> {
+        self.hackrf.set_amp_enable(en)
+    }
+
+    pub fn set_baseband_filter_bandwidth(&mut self, hz: u32) -> Result<(), Error> {
+        self.hackrf.set_baseband_filter_bandwidth(hz)
+    }
+
+    pub fn set_lna_gain(&mut self, value: u16) -> Result<(), Error> {
+        self.hackrf.set_lna_gain(value)
+    }
+
+    pub fn set_vga_gain(&mut self, value: u16) -> Result<(), Error> {
+        self.hackrf.set_vga_gain(value)
+    }
+
+    pub fn rx(&mut self) -> Result<Vec<u8>, Error> {
+        let mut buffer: Vec<u8> = vec![0; MAX_TRANSMISSION_UNIT];
+        let n: usize = self.hackrf.transport.read_bulk(
+            RX_ENDPOINT_ADDRESS,
+            &mut buffer,
+            self.hackrf.timeout,
+        )?;
+        buffer.truncate(n);
+
+        Ok(buffer)
+    }
+
+    pub fn rx_stream(&mut self, config: StreamConfig) -> Result<RxStream<'_>, Error> {
+        RxStream::new(&self.hackrf, config)
+    }
+
+    #[cfg(feature = "async")]
+    pub fn into_iq_stream(self, config: StreamConfig) -> IqStream {
+        IqStream::spawn(self, config)
+    }
+
+    pub fn stop(mut self) -> Result<HackRF, (Receiver, Error)> {
+        match self.hackrf.stop() {
+            Ok(()) => Ok(self.hackrf),
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+
+// A device in transmit mode, the counterpart of `Receiver`.
+#[derive(Debug)]
+pub struct Transmitter {
+    hackrf: HackRF,
+}
+
+impl Transmitter {
+    pub(crate) fn new(hackrf: HackRF) -> Transmitter {
+        Transmitter { hackrf }
+    }
+
+    pub fn device(&self) -> &HackRF {
+        &self.hackrf
+    }
+
+    pub fn set_freq(&mut self, hz: u64) -> Result<(), Error> {
+        self.hackrf.set_freq(hz)
+    }
+
+    pub fn set_amp_enable(&mut self, en: bool) -> Result<(), Error> {
+        self.hackrf.set_amp_enable(en)
+    }
+
+    pub fn set_baseband_filter_bandwidth(&mut self, hz: u32) -> Result<(), Error> {
+        self.hackrf.set_baseband_filter_bandwidth(hz)
+    }
+
+    pub fn set_txvga_gain(&mut self, value: u16) -> Result<(), Error> {
+        self.hackrf.set_txvga_gain(value)
+    }
+
+    pub fn tx(&mut self, mut buffer: Vec<u8>) -> Result<(), Error> {
+        buffer.truncate(MAX_TRANSMISSION_UNIT);
+        self.hackrf
+            .transport
+            .write_bulk(TX_ENDPOINT_ADDRESS, &buffer, self.hackrf.timeout)?;
+
+        Ok(())
+    }
+
+    pub fn tx_stream(&mut self, config: StreamConfig) -> Result<TxStream<'_>, Error> {
+        TxStream::new(&self.hackrf, config)
+    }
+
+    pub fn stop(mut self) -> Result<HackRF, (Transmitter, Error)> {
+        match self.hackrf.stop() {
+            Ok(()) => Ok(self.hackrf),
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+
+// A device running the sweep set up with `HackRF::init_sweep`. The firmware
+// retunes on its own, so only the gains can be changed.
+#[derive(Debug)]
+pub struct Sweeper {
+    hackrf: HackRF,
+    offset: u32,
+}
+
+impl Sweeper {
+    pub(crate) fn new(hackrf: HackRF, offset: u32) -> Sweeper {
+        Sweeper { hackrf, offset }
+    }
+
+    pub fn device(&self) -> &HackRF {
+        &self.hackrf
+    }
+
+    pub fn set_amp_enable(&mut self, en: bool) -> Result<(), Error> {
+        self.hackrf.set_amp_enable(en)
+    }
+
+    pub fn set_lna_gain(&mut self, value: u16) -> Result<(), Error> {
+        self.hackrf.set_lna_gain(value)
+    }
+
+    pub fn set_vga_gain(&mut self, value: u16) -> Result<(), Error> {
+        self.hackrf.set_vga_gain(value)
+    }
+
+    // Transfers are split into the firmware's 16 KiB blocks, so their size
+    // has to be a multiple of that.
+    pub fn blocks(&mut self, config: StreamConfig) -> Result<SweepBlocks<'_>, Error> {
+        if !config.transfer_size.is_multiple_of(SWEEP_BLOCK_SIZE) {
+            return Err(Error::Argument);
+        }
+        Ok(SweepBlocks::new(
+            RxStream::new(&self.hackrf, config)?,
+            self.offset,
+        ))
+    }
+
+    // the error carries the whole sweeper so a failed stop can be retried
+    #[allow(clippy::result_large_err)]
+    pub fn stop(mut self) -> Result<HackRF, (Sweeper, Error)> {
+        match self.hackrf.stop() {
+            Ok(()) => Ok(self.hackrf),
+            Err(e) => Err((self, e)),
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use crate::Error;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum TransceiverMode {
     Off = 0,
@@ -19,3 +22,35 @@ impl From<TransceiverMode> for u16 {
         tm as u16
     }
 }
+
+impl TryFrom<u16> for TransceiverMode {
+    type Error = Error;
+
+    fn try_from(value: u16) -> Result<Self, Error> {
+        match value {
+            0 => Ok(TransceiverMode::Off),
+            1 => Ok(TransceiverMode::Receive),
+            2 => Ok(TransceiverMode::Transmit),
+            3 => Ok(TransceiverMode::Ss),
+            4 => Ok(TransceiverMode::CpldUpdate),
+            5 => Ok(TransceiverMode::RxSweep),
+            _ => Err(Error::Argument),
+        }
+    }
+}
+
+// With sync on, a board entering RX or TX waits for a rising edge on its
+// trigger input before it starts moving samples. The setting sticks for
+// every later start until it is switched off again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HwSyncMode {
+    Off = 0,
+    On = 1,
+}
+
+impl From<HwSyncMode> for u16 {
+    fn from(mode: HwSyncMode) -> Self {
+        mode as u16
+    }
+}
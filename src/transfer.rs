@@ -0,0 +1,232 @@
+use crate::transport::BulkStream;
+use crate::Error;
+use rusb::constants::*;
+use rusb::ffi::{self, libusb_transfer};
+use rusb::{DeviceHandle, GlobalContext, UsbContext};
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// how long cancelled transfers get to come back before their buffers are leaked
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransferStatus {
+    Complete,
+    Short,
+    Lost,
+}
+
+struct Slot {
+    transfer: NonNull<libusb_transfer>,
+    buffer: Vec<u8>,
+    // boxed so the address handed to libusb survives the slot being moved
+    completed: Box<AtomicBool>,
+    in_flight: bool,
+}
+
+// A ring of libusb bulk transfers on one endpoint. Transfers on an endpoint
+// complete in submission order, so the oldest in-flight transfer is always
+// the next one to wait for.
+pub(crate) struct TransferPool {
+    handle: *mut ffi::libusb_device_handle,
+    endpoint: u8,
+    slots: Vec<Slot>,
+}
+
+extern "system" fn transfer_callback(transfer: *mut libusb_transfer) {
+    unsafe {
+        let completed: *const AtomicBool = (*transfer).user_data as *const AtomicBool;
+        (*completed).store(true, Ordering::Release);
+    }
+}
+
+fn libusb_error(code: i32) -> Error {
+    Error::Usb(match code {
+        LIBUSB_ERROR_IO => rusb::Error::Io,
+        LIBUSB_ERROR_INVALID_PARAM => rusb::Error::InvalidParam,
+        LIBUSB_ERROR_ACCESS => rusb::Error::Access,
+        LIBUSB_ERROR_NO_DEVICE => rusb::Error::NoDevice,
+        LIBUSB_ERROR_NOT_FOUND => rusb::Error::NotFound,
+        LIBUSB_ERROR_BUSY => rusb::Error::Busy,
+        LIBUSB_ERROR_TIMEOUT => rusb::Error::Timeout,
+        LIBUSB_ERROR_OVERFLOW => rusb::Error::Overflow,
+        LIBUSB_ERROR_PIPE => rusb::Error::Pipe,
+        LIBUSB_ERROR_INTERRUPTED => rusb::Error::Interrupted,
+        LIBUSB_ERROR_NO_MEM => rusb::Error::NoMem,
+        LIBUSB_ERROR_NOT_SUPPORTED => rusb::Error::NotSupported,
+        _ => rusb::Error::Other,
+    })
+}
+
+impl TransferPool {
+    pub(crate) fn new(
+        handle: &DeviceHandle<GlobalContext>,
+        endpoint: u8,
+        count: usize,
+        size: usize,
+    ) -> Result<TransferPool, Error> {
+        let mut pool: TransferPool = TransferPool {
+            handle: handle.as_raw(),
+            endpoint,
+            slots: Vec::with_capacity(count),
+        };
+
+        for _ in 0..count {
+            let transfer: NonNull<libusb_transfer> =
+                NonNull::new(unsafe { ffi::libusb_alloc_transfer(0) })
+                    .ok_or(Error::Usb(rusb::Error::NoMem))?;
+            pool.slots.push(Slot {
+                transfer,
+                buffer: vec![0; size],
+                completed: Box::new(AtomicBool::new(false)),
+                in_flight: false,
+            });
+        }
+        Ok(pool)
+    }
+
+    fn cancel(&mut self) {
+        for slot in self.slots.iter().filter(|slot| slot.in_flight) {
+            unsafe {
+                ffi::libusb_cancel_transfer(slot.transfer.as_ptr());
+            }
+        }
+
+        let deadline: Instant = Instant::now() + CANCEL_TIMEOUT;
+        for slot in self.slots.iter_mut().filter(|slot| slot.in_flight) {
+            while !slot.completed.load(Ordering::Acquire) {
+                let now: Instant = Instant::now();
+                if now >= deadline
+                    || GlobalContext::default()
+                        .handle_events(Some(deadline - now))
+                        .is_err()
+                {
+                    break;
+                }
+            }
+            slot.in_flight = !slot.completed.load(Ordering::Acquire);
+        }
+    }
+}
+
+impl BulkStream for TransferPool {
+    fn transfer_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn in_flight(&self, index: usize) -> bool {
+        self.slots[index].in_flight
+    }
+
+    fn pending(&self) -> usize {
+        // run any completion callbacks that are due without blocking
+        let _ = GlobalContext::default().handle_events(Some(Duration::ZERO));
+        self.slots
+            .iter()
+            .filter(|slot| slot.in_flight && !slot.completed.load(Ordering::Acquire))
+            .count()
+    }
+
+    // libusb writes into the buffer of an in-flight transfer at any time, so
+    // it must not be lent out until `wait` has returned for it
+    fn buffer(&self, index: usize) -> &[u8] {
+        assert!(
+            !self.slots[index].in_flight,
+            "transfer {} is in flight",
+            index
+        );
+        &self.slots[index].buffer
+    }
+
+    fn buffer_mut(&mut self, index: usize) -> &mut [u8] {
+        assert!(
+            !self.slots[index].in_flight,
+            "transfer {} is in flight",
+            index
+        );
+        &mut self.slots[index].buffer
+    }
+
+    fn submit(&mut self, index: usize, len: usize) -> Result<(), Error> {
+        let handle: *mut ffi::libusb_device_handle = self.handle;
+        let endpoint: u8 = self.endpoint;
+        let slot: &mut Slot = &mut self.slots[index];
+        if slot.in_flight {
+            return Err(Error::Busy);
+        }
+        let len: usize = len.min(slot.buffer.len());
+
+        slot.completed.store(false, Ordering::Release);
+        let result: i32 = unsafe {
+            ffi::libusb_fill_bulk_transfer(
+                slot.transfer.as_ptr(),
+                handle,
+                endpoint,
+                slot.buffer.as_mut_ptr(),
+                len as i32,
+                transfer_callback,
+                &*slot.completed as *const AtomicBool as *mut c_void,
+                0,
+            );
+            ffi::libusb_submit_transfer(slot.transfer.as_ptr())
+        };
+
+        if result == 0 {
+            slot.in_flight = true;
+            Ok(())
+        } else {
+            Err(libusb_error(result))
+        }
+    }
+
+    fn wait(&mut self, index: usize, timeout: Duration) -> Result<(), Error> {
+        let deadline: Instant = Instant::now() + timeout;
+        let slot: &Slot = &self.slots[index];
+
+        while !slot.completed.load(Ordering::Acquire) {
+            let now: Instant = Instant::now();
+            if now >= deadline {
+                return Err(Error::Usb(rusb::Error::Timeout));
+            }
+            GlobalContext::default().handle_events(Some(deadline - now))?;
+        }
+        self.slots[index].in_flight = false;
+        Ok(())
+    }
+
+    // result of a transfer that `wait` has returned for, with the number of
+    // bytes actually moved
+    fn status(&self, index: usize) -> Result<(TransferStatus, usize), Error> {
+        let transfer: &libusb_transfer = unsafe { self.slots[index].transfer.as_ref() };
+        let actual: usize = transfer.actual_length.max(0) as usize;
+
+        match transfer.status {
+            LIBUSB_TRANSFER_COMPLETED if actual == transfer.length as usize => {
+                Ok((TransferStatus::Complete, actual))
+            }
+            LIBUSB_TRANSFER_COMPLETED => Ok((TransferStatus::Short, actual)),
+            LIBUSB_TRANSFER_NO_DEVICE => Err(Error::Usb(rusb::Error::NoDevice)),
+            LIBUSB_TRANSFER_CANCELLED => Err(Error::Usb(rusb::Error::Interrupted)),
+            _ => Ok((TransferStatus::Lost, 0)),
+        }
+    }
+}
+
+impl Drop for TransferPool {
+    fn drop(&mut self) {
+        self.cancel();
+
+        for slot in self.slots.drain(..) {
+            if slot.in_flight {
+                // libusb still owns this transfer, freeing its buffer would
+                // let a late completion write into released memory
+                log::warn!("Leaking a bulk transfer that could not be cancelled");
+                std::mem::forget(slot);
+            } else {
+                unsafe { ffi::libusb_free_transfer(slot.transfer.as_ptr()) };
+            }
+        }
+    }
+}
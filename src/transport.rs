@@ -0,0 +1,293 @@
+use crate::request::Request;
+use crate::stream::StreamConfig;
+use crate::transfer::{TransferPool, TransferStatus};
+use crate::{Error, HackRF};
+use rusb::{
+    request_type, DeviceDescriptor, DeviceHandle, Direction, GlobalContext, Recipient, RequestType,
+    Version,
+};
+use std::time::Duration;
+
+// Everything the driver needs from a device. `UsbTransport` talks to real
+// hardware through rusb; other implementations stand in for it in tests.
+pub trait Transport: Send + std::fmt::Debug {
+    fn device_version(&self) -> Version;
+
+    fn product_id(&self) -> u16;
+
+    fn read_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, Error>;
+
+    fn write_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, Error>;
+
+    fn read_bulk(&self, endpoint: u8, buffer: &mut [u8], timeout: Duration)
+        -> Result<usize, Error>;
+
+    fn write_bulk(&self, endpoint: u8, buffer: &[u8], timeout: Duration) -> Result<usize, Error>;
+
+    fn claim_interface(&self, interface: u8) -> Result<(), Error>;
+
+    fn release_interface(&self, interface: u8) -> Result<(), Error>;
+
+    // Transports without asynchronous transfers get a stream that performs
+    // one blocking bulk transfer per slot.
+    fn bulk_stream(
+        &self,
+        endpoint: u8,
+        config: StreamConfig,
+    ) -> Result<Box<dyn BulkStream + '_>, Error> {
+        Ok(Box::new(SyncBulkStream::new(self, endpoint, config)))
+    }
+}
+
+// A fixed set of transfer slots on one endpoint. Slots are submitted and
+// waited for in ring order by the RX/TX streams.
+pub trait BulkStream {
+    fn transfer_count(&self) -> usize;
+
+    fn in_flight(&self, index: usize) -> bool;
+
+    // transfers still waiting for the bus, as opposed to finished ones that
+    // have not been waited for yet
+    fn pending(&self) -> usize;
+
+    // Buffers of in-flight slots belong to the bus; implementations panic
+    // rather than hand them out.
+    fn buffer(&self, index: usize) -> &[u8];
+
+    fn buffer_mut(&mut self, index: usize) -> &mut [u8];
+
+    fn submit(&mut self, index: usize, len: usize) -> Result<(), Error>;
+
+    fn wait(&mut self, index: usize, timeout: Duration) -> Result<(), Error>;
+
+    fn status(&self, index: usize) -> Result<(TransferStatus, usize), Error>;
+}
+
+#[derive(Debug)]
+pub struct UsbTransport {
+    device_handle: DeviceHandle<GlobalContext>,
+    description: DeviceDescriptor,
+}
+
+impl UsbTransport {
+    pub fn new(device_handle: DeviceHandle<GlobalContext>, description: DeviceDescriptor) -> Self {
+        UsbTransport {
+            device_handle,
+            description,
+        }
+    }
+
+    // The first board on the bus, for wrapping in another transport.
+    pub fn open_first() -> Result<UsbTransport, Error> {
+        let (device, description) = HackRF::usb_devices()?
+            .into_iter()
+            .next()
+            .ok_or(Error::NotFound)?;
+        let device_handle: DeviceHandle<GlobalContext> = device
+            .open()
+            .map_err(|e| HackRF::open_error(Error::Usb(e)))?;
+        Ok(UsbTransport::new(device_handle, description))
+    }
+}
+
+impl Transport for UsbTransport {
+    fn device_version(&self) -> Version {
+        self.description.device_version()
+    }
+
+    fn product_id(&self) -> u16 {
+        self.description.product_id()
+    }
+
+    fn read_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, Error> {
+        Ok(self.device_handle.read_control(
+            request_type(Direction::In, RequestType::Vendor, Recipient::Device),
+            request.into(),
+            value,
+            index,
+            buffer,
+            timeout,
+        )?)
+    }
+
+    fn write_control(
+        &self,
+        request: Request,
+        value: u16,
+        index: u16,
+        buffer: &[u8],
+        timeout: Duration,
+    ) -> Result<usize, Error> {
+        Ok(self.device_handle.write_control(
+            request_type(Direction::Out, RequestType::Vendor, Recipient::Device),
+            request.into(),
+            value,
+            index,
+            buffer,
+            timeout,
+        )?)
+    }
+
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        buffer: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, Error> {
+        Ok(self.device_handle.read_bulk(endpoint, buffer, timeout)?)
+    }
+
+    fn write_bulk(&self, endpoint: u8, buffer: &[u8], timeout: Duration) -> Result<usize, Error> {
+        Ok(self.device_handle.write_bulk(endpoint, buffer, timeout)?)
+    }
+
+    fn claim_interface(&self, interface: u8) -> Result<(), Error> {
+        Ok(self.device_handle.claim_interface(interface)?)
+    }
+
+    fn release_interface(&self, interface: u8) -> Result<(), Error> {
+        Ok(self.device_handle.release_interface(interface)?)
+    }
+
+    fn bulk_stream(
+        &self,
+        endpoint: u8,
+        config: StreamConfig,
+    ) -> Result<Box<dyn BulkStream + '_>, Error> {
+        Ok(Box::new(TransferPool::new(
+            &self.device_handle,
+            endpoint,
+            config.transfer_count,
+            config.transfer_size,
+        )?))
+    }
+}
+
+struct SyncSlot {
+    buffer: Vec<u8>,
+    len: usize,
+    result: Option<usize>,
+    in_flight: bool,
+}
+
+struct SyncBulkStream<'a, T: Transport + ?Sized> {
+    transport: &'a T,
+    endpoint: u8,
+    slots: Vec<SyncSlot>,
+}
+
+impl<'a, T: Transport + ?Sized> SyncBulkStream<'a, T> {
+    fn new(transport: &'a T, endpoint: u8, config: StreamConfig) -> Self {
+        SyncBulkStream {
+            transport,
+            endpoint,
+            slots: (0..config.transfer_count)
+                .map(|_| SyncSlot {
+                    buffer: vec![0; config.transfer_size],
+                    len: 0,
+                    result: None,
+                    in_flight: false,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T: Transport + ?Sized> BulkStream for SyncBulkStream<'_, T> {
+    fn transfer_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn in_flight(&self, index: usize) -> bool {
+        self.slots[index].in_flight
+    }
+
+    // nothing moves until `wait`, so every submitted slot is still pending
+    fn pending(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.in_flight).count()
+    }
+
+    fn buffer(&self, index: usize) -> &[u8] {
+        assert!(
+            !self.slots[index].in_flight,
+            "transfer {} is in flight",
+            index
+        );
+        &self.slots[index].buffer
+    }
+
+    fn buffer_mut(&mut self, index: usize) -> &mut [u8] {
+        assert!(
+            !self.slots[index].in_flight,
+            "transfer {} is in flight",
+            index
+        );
+        &mut self.slots[index].buffer
+    }
+
+    fn submit(&mut self, index: usize, len: usize) -> Result<(), Error> {
+        let slot: &mut SyncSlot = &mut self.slots[index];
+        if slot.in_flight {
+            return Err(Error::Busy);
+        }
+        slot.len = len.min(slot.buffer.len());
+        slot.result = None;
+        slot.in_flight = true;
+        Ok(())
+    }
+
+    // The transfer itself happens here, so slots still move in ring order.
+    fn wait(&mut self, index: usize, timeout: Duration) -> Result<(), Error> {
+        let endpoint: u8 = self.endpoint;
+        let slot: &mut SyncSlot = &mut self.slots[index];
+        if !slot.in_flight {
+            return Ok(());
+        }
+        let result: Result<usize, Error> = if endpoint & rusb::constants::LIBUSB_ENDPOINT_IN != 0 {
+            self.transport
+                .read_bulk(endpoint, &mut slot.buffer[..slot.len], timeout)
+        } else {
+            self.transport
+                .write_bulk(endpoint, &slot.buffer[..slot.len], timeout)
+        };
+        // the same split `TransferPool` gets from libusb: a transfer that
+        // failed on the bus is lost, anything else ends the stream
+        slot.result = match result {
+            Ok(n) => Some(n),
+            Err(Error::Usb(rusb::Error::Io | rusb::Error::Pipe | rusb::Error::Overflow)) => None,
+            Err(e) => return Err(e),
+        };
+        slot.in_flight = false;
+        Ok(())
+    }
+
+    fn status(&self, index: usize) -> Result<(TransferStatus, usize), Error> {
+        let slot: &SyncSlot = &self.slots[index];
+        match slot.result {
+            Some(n) if n == slot.len => Ok((TransferStatus::Complete, n)),
+            Some(n) => Ok((TransferStatus::Short, n)),
+            None => Ok((TransferStatus::Lost, 0)),
+        }
+    }
+}
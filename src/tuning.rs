@@ -0,0 +1,123 @@
+use crate::constants::MHZ;
+use crate::Error;
+
+// range the MAX2837 can be tuned to as an IF
+const IF_MIN_HZ: u64 = 2_150 * MHZ;
+const IF_MAX_HZ: u64 = 2_750 * MHZ;
+const LO_MIN_HZ: u64 = 84_375_000;
+const LO_MAX_HZ: u64 = 5_400 * MHZ;
+
+// Boundaries of the filter bands the firmware picks from in `set_freq`.
+const MAX_LP_FREQ_MHZ: u64 = 2_170;
+const MAX_BYPASS_FREQ_MHZ: u64 = 2_740;
+const MID1_HP_FREQ_MHZ: u64 = 3_600;
+const MID2_HP_FREQ_MHZ: u64 = 5_100;
+const MAX_HP_FREQ_MHZ: u64 = 7_250;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RfPathFilter {
+    // the MAX2837 is tuned straight to the RF frequency, the mixer is off
+    Bypass = 0,
+    // RF = LO - IF, for frequencies below the IF range
+    LowPass = 1,
+    // RF = LO + IF, for frequencies above the IF range
+    HighPass = 2,
+}
+
+impl From<RfPathFilter> for u8 {
+    fn from(path: RfPathFilter) -> Self {
+        path as u8
+    }
+}
+
+impl TryFrom<u8> for RfPathFilter {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Error> {
+        match value {
+            0 => Ok(RfPathFilter::Bypass),
+            1 => Ok(RfPathFilter::LowPass),
+            2 => Ok(RfPathFilter::HighPass),
+            _ => Err(Error::Argument),
+        }
+    }
+}
+
+// The IF, LO and filter path that put the radio on one RF frequency; the
+// arguments of `HackRF::set_freq_explicit`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FreqPlan {
+    pub if_hz: u64,
+    pub lo_hz: u64,
+    pub path: RfPathFilter,
+}
+
+impl FreqPlan {
+    // The plan the firmware picks for `set_freq(hz)`. The IF slides across
+    // its range as the frequency rises so mixer products stay out of band.
+    // The firmware then adjusts the IF for the LO the RFFC5071 actually
+    // locks to, so the realised IF can differ from this by a few Hz.
+    pub fn for_freq(hz: u64) -> Result<FreqPlan, Error> {
+        let freq_mhz: u64 = hz / MHZ;
+
+        if freq_mhz < MAX_LP_FREQ_MHZ {
+            let if_nominal: u64 = 2_650 * MHZ - hz / 7;
+            let lo_hz: u64 = (if_nominal / MHZ + freq_mhz) * MHZ;
+            Ok(FreqPlan {
+                if_hz: lo_hz - hz,
+                lo_hz,
+                path: RfPathFilter::LowPass,
+            })
+        } else if freq_mhz < MAX_BYPASS_FREQ_MHZ {
+            Ok(FreqPlan {
+                if_hz: hz,
+                lo_hz: 0,
+                path: RfPathFilter::Bypass,
+            })
+        } else if freq_mhz <= MAX_HP_FREQ_MHZ {
+            let if_nominal: u64 = if freq_mhz < MID1_HP_FREQ_MHZ {
+                2_170 * MHZ + (hz - MAX_BYPASS_FREQ_MHZ * MHZ) * 57 / 86
+            } else if freq_mhz < MID2_HP_FREQ_MHZ {
+                2_350 * MHZ + (hz - MID1_HP_FREQ_MHZ * MHZ) / 5
+            } else {
+                2_500 * MHZ + (hz - MID2_HP_FREQ_MHZ * MHZ) / 9
+            };
+            let lo_hz: u64 = (freq_mhz - if_nominal / MHZ) * MHZ;
+            Ok(FreqPlan {
+                if_hz: hz - lo_hz,
+                lo_hz,
+                path: RfPathFilter::HighPass,
+            })
+        } else {
+            Err(Error::Argument)
+        }
+    }
+
+    // the RF frequency this plan receives or transmits on
+    pub fn rf_hz(&self) -> u64 {
+        match self.path {
+            RfPathFilter::Bypass => self.if_hz,
+            RfPathFilter::LowPass => self.lo_hz.saturating_sub(self.if_hz),
+            RfPathFilter::HighPass => self.lo_hz + self.if_hz,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if !(IF_MIN_HZ..=IF_MAX_HZ).contains(&self.if_hz)
+            || (self.path != RfPathFilter::Bypass && !(LO_MIN_HZ..=LO_MAX_HZ).contains(&self.lo_hz))
+        {
+            Err(Error::Argument)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 17] {
+        let mut buffer: [u8; 17] = [0; 17];
+        buffer[0..8].copy_from_slice(&self.if_hz.to_le_bytes());
+        buffer[8..16].copy_from_slice(&self.lo_hz.to_le_bytes());
+        buffer[16] = self.path.into();
+        buffer
+    }
+}